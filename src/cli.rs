@@ -44,6 +44,9 @@ pub enum Commands {
     /// Build release notes from a configuration directory.
     #[bpaf(command)]
     Build {
+        /// Build the release notes in this language, using the `translations/LANG.po` file.
+        #[bpaf(short, long, argument("LANG"))]
+        lang: Option<String>,
         /// Path to the configuration directory. The default is the current working directory.
         #[bpaf(positional::<PathBuf>("DIR"), fallback(".".into()))]
         project: PathBuf,
@@ -112,6 +115,17 @@ pub enum Commands {
         )]
         directory: PathBuf,
     },
+    /// Migrate a project from the legacy `cizrna` directory to the current one.
+    #[bpaf(command)]
+    Migrate {
+        /// Rename the directory and rewrite the AsciiDoc files.
+        /// Without this option, only display the changes.
+        #[bpaf(short, long, switch)]
+        apply: bool,
+        /// Path to the project directory. The default is the current working directory.
+        #[bpaf(positional::<PathBuf>("DIR"), fallback(".".into()))]
+        project: PathBuf,
+    },
 }
 
 /// Calculate the length of a vector for repeating flags, such as verbosity.
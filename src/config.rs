@@ -16,25 +16,29 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use std::collections::HashMap;
 use std::convert::From;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use color_eyre::eyre::{eyre, Result, WrapErr};
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+use globset::{GlobBuilder, GlobMatcher};
+use regex::{Regex, RegexBuilder};
 use serde::Deserialize;
 
+use crate::custom_templates::CustomTemplates;
 use crate::footnote;
 
 /// The name of this program, as specified in Cargo.toml. Used later to access configuration files.
 const PROGRAM_NAME: &str = env!("CARGO_PKG_NAME");
 
 /// The previous name of this program. Used for compatibility purposes.
-const LEGACY_NAME: &str = "cizrna";
+pub const LEGACY_NAME: &str = "cizrna";
 
 /// The sub-directory inside the release notes project that contains all aCoRNs configuration and other files.
 /// The name of this sub-directory is the same as the name of this program.
-const DATA_PREFIX: &str = PROGRAM_NAME;
+pub const DATA_PREFIX: &str = PROGRAM_NAME;
 
 // TODO: Make the output configurable. Enable saving to a separate Git repository.
 /// The sub-directory inside the data directory that contains all generated documents.
@@ -228,6 +232,27 @@ pub mod tracker {
         pub target_release: Option<Vec<String>>,
     }
 
+    /// Custom values of the doc text status field, which map to the statuses
+    /// that acorns recognizes. These take precedence over the built-in values.
+    #[derive(Debug, Default, Eq, PartialEq, Deserialize)]
+    #[serde(default, deny_unknown_fields)]
+    pub struct DocTextStatusValues {
+        pub approved: Vec<String>,
+        pub in_progress: Vec<String>,
+        pub no_documentation: Vec<String>,
+    }
+
+    /// The markup language that engineers use in the doc text field.
+    /// Acorns converts the doc text to AsciiDoc.
+    #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub enum DocTextFormat {
+        #[default]
+        Asciidoc,
+        JiraWiki,
+        Markdown,
+    }
+
     /// The particular instance of an issue tracker,
     /// with a host URL and access credentials.
     #[derive(Debug, Eq, PartialEq, Deserialize)]
@@ -236,6 +261,10 @@ pub mod tracker {
         pub host: String,
         pub api_key: Option<String>,
         pub fields: BugzillaFields,
+        #[serde(default)]
+        pub doc_text_status_values: DocTextStatusValues,
+        #[serde(default)]
+        pub doc_text_format: DocTextFormat,
     }
 
     /// The particular instance of an issue tracker,
@@ -248,6 +277,10 @@ pub mod tracker {
         #[serde(default)]
         pub private_projects: Vec<String>,
         pub fields: JiraFields,
+        #[serde(default)]
+        pub doc_text_status_values: DocTextStatusValues,
+        #[serde(default)]
+        pub doc_text_format: DocTextFormat,
     }
 
     /// The issue tracker instances configured in the current release notes project.
@@ -275,6 +308,8 @@ pub mod tracker {
         fn docs_contact(&self) -> &[String];
         /// The configured URL to the instance host.
         fn host(&self) -> &str;
+        /// The configured custom values of the doc text status field.
+        fn doc_text_status_values(&self) -> &DocTextStatusValues;
     }
 
     impl FieldsConfig for BugzillaInstance {
@@ -310,6 +345,9 @@ pub mod tracker {
         fn host(&self) -> &str {
             &self.host
         }
+        fn doc_text_status_values(&self) -> &DocTextStatusValues {
+            &self.doc_text_status_values
+        }
     }
 
     impl FieldsConfig for JiraInstance {
@@ -342,6 +380,9 @@ pub mod tracker {
         fn host(&self) -> &str {
             &self.host
         }
+        fn doc_text_status_values(&self) -> &DocTextStatusValues {
+            &self.doc_text_status_values
+        }
     }
 }
 
@@ -350,12 +391,31 @@ pub mod tracker {
 /// in YaML to create reusable section definitions that can then
 /// appear several times in different places. They have to be defined
 /// on the top level, outside the actual chapters.
-#[derive(Debug, PartialEq, Deserialize)]
+///
+/// A reusable section can either be referenced by a YaML anchor,
+/// or by its `name` in the `use` field of another section.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Template {
     pub chapters: Vec<Section>,
     #[serde(alias = "sections")]
     pub subsections: Option<Vec<Section>>,
+    #[serde(default)]
+    pub placement: Placement,
+    /// The file names of generated modules, with the `{type}` and `{id}` placeholders.
+    pub file_name_pattern: Option<String>,
+}
+
+/// How many sections can include the same ticket.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Placement {
+    /// Every section whose filter matches includes the ticket.
+    /// Repeated copies of the release note receive unique anchors.
+    #[default]
+    All,
+    /// Only the first section in document order whose filter matches includes the ticket.
+    FirstMatch,
 }
 
 /// This struct covers the necessary properties of a section, which can either
@@ -364,24 +424,573 @@ pub struct Template {
 ///
 /// The `filter` field narrows down the tickets that can appear in this module
 /// or in the modules that are included in this assembly.
-#[derive(Debug, PartialEq, Deserialize)]
+///
+/// A `catch_all` section further narrows down the tickets to those that no regular
+/// section includes, after evaluating the whole template.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Section {
+    /// The title can only be empty in a section that uses a reusable section.
+    /// It then keeps the title of the reusable section.
+    #[serde(default)]
     pub title: String,
+    /// The name of a reusable section, which other sections can `use`.
+    pub name: Option<String>,
+    /// The name of a reusable section that this section copies. The properties
+    /// that this section sets override the properties of the reusable section.
+    #[serde(rename = "use")]
+    pub reuse: Option<String>,
+    /// A stable ID that replaces the ID generated from the title.
+    pub id: Option<String>,
+    /// A file name that replaces the file name generated from the ID.
+    pub file_name: Option<String>,
     pub intro_abstract: Option<String>,
+    #[serde(default)]
     pub filter: Filter,
     #[serde(alias = "sections")]
     pub subsections: Option<Vec<Section>>,
+    /// Collect the tickets that no other section includes.
+    pub catch_all: Option<bool>,
+    /// The order of release notes in this section and in its subsections.
+    pub sort_by: Option<Vec<SortKey>>,
+    /// Arrange the release notes of a leaf section under a subheading for each value of the field.
+    pub subheadings: Option<TicketField>,
+    /// Generate a subsection for each value of the field among the tickets in this section.
+    pub group_by: Option<TicketField>,
+    /// The title of each generated subsection, where `{value}` is the value of the field.
+    pub group_title: Option<String>,
+    /// The value that the subheading or the generated subsection with no value displays.
+    pub other_title: Option<String>,
+}
+
+/// A ticket field whose values can organize the release notes in a section.
+/// Subheadings only use the first value of the field.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TicketField {
+    /// The components of the ticket.
+    Component,
+    /// The subsystems of the ticket.
+    Subsystem,
+    /// The product of the ticket.
+    Product,
+    /// The doc type of the ticket.
+    DocType,
 }
 
 /// The configuration of a filter, which narrows down the tickets
 /// that can appear in the section that the filter belongs to.
-#[derive(Debug, Eq, PartialEq, Deserialize)]
+///
+/// A ticket must match all the configured properties. The `any`, `all`, and `not`
+/// combinators nest other filters, so that you can build a boolean expression.
+/// An empty filter matches all tickets.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Filter {
-    pub doc_type: Option<Vec<String>>,
-    pub subsystem: Option<Vec<String>>,
-    pub component: Option<Vec<String>>,
+    pub doc_type: Option<Vec<Pattern>>,
+    pub subsystem: Option<Vec<Pattern>>,
+    pub component: Option<Vec<Pattern>>,
+    pub label: Option<Vec<Pattern>>,
+    /// Matches either the complete `name: value` flag, or only its name.
+    pub flag: Option<Vec<Pattern>>,
+    pub priority: Option<Vec<Pattern>>,
+    pub product: Option<Vec<Pattern>>,
+    pub target_release: Option<Vec<Pattern>>,
+    pub status: Option<Vec<Pattern>>,
+    /// Matches either only public tickets, or only private tickets.
+    pub public: Option<bool>,
+    /// Matches if at least one of the nested filters matches.
+    pub any: Option<Vec<Filter>>,
+    /// Matches if all of the nested filters match.
+    pub all: Option<Vec<Filter>>,
+    /// Matches if the nested filter doesn't match.
+    pub not: Option<Box<Filter>>,
+}
+
+impl Template {
+    /// Replace every section that uses a reusable section with a copy of the reusable section,
+    /// combined with the properties that the using section overrides.
+    fn resolve_reused_sections(&mut self) -> Result<()> {
+        let named: HashMap<String, Section> = self
+            .subsections
+            .iter()
+            .flatten()
+            .filter_map(|section| {
+                section
+                    .name
+                    .as_ref()
+                    .map(|name| (name.clone(), section.clone()))
+            })
+            .collect();
+
+        for chapter in &mut self.chapters {
+            chapter.resolve(&named, &mut Vec::new())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Section {
+    /// Resolve the `use` field of this section and of its subsections, recursively.
+    /// The `stack` lists the reusable sections that are being resolved, to detect cycles.
+    fn resolve(&mut self, named: &HashMap<String, Section>, stack: &mut Vec<String>) -> Result<()> {
+        if let Some(name) = self.reuse.take() {
+            if stack.contains(&name) {
+                bail!("The reusable section `{name}` uses itself.");
+            }
+
+            let mut base = named
+                .get(&name)
+                .ok_or_else(|| eyre!("No reusable section is named `{name}`."))?
+                .clone();
+
+            stack.push(name);
+            base.resolve(named, stack)?;
+            stack.pop();
+
+            self.inherit(base);
+        }
+
+        if self.title.is_empty() {
+            bail!("A section in the templates has no title.");
+        }
+
+        if self.group_by.is_some() && self.subsections.is_some() {
+            bail!(
+                "The `{}` section generates its subsections, so it cannot list any.",
+                self.title
+            );
+        }
+
+        if self.subheadings.is_some() && self.subsections.is_some() {
+            bail!(
+                "The `{}` section has subsections, so it cannot use subheadings.",
+                self.title
+            );
+        }
+
+        if let Some(subsections) = &mut self.subsections {
+            for subsection in subsections {
+                subsection.resolve(named, stack)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fill in the properties that this section doesn't set from the reusable section.
+    /// An empty filter counts as not set.
+    fn inherit(&mut self, base: Section) {
+        if self.title.is_empty() {
+            self.title = base.title;
+        }
+        if self.filter == Filter::default() {
+            self.filter = base.filter;
+        }
+        self.id = self.id.take().or(base.id);
+        self.file_name = self.file_name.take().or(base.file_name);
+        self.intro_abstract = self.intro_abstract.take().or(base.intro_abstract);
+        self.subsections = self.subsections.take().or(base.subsections);
+        self.sort_by = self.sort_by.take().or(base.sort_by);
+        self.subheadings = self.subheadings.or(base.subheadings);
+        self.group_by = self.group_by.or(base.group_by);
+        self.group_title = self.group_title.take().or(base.group_title);
+        self.other_title = self.other_title.take().or(base.other_title);
+        self.catch_all = self.catch_all.or(base.catch_all);
+    }
+}
+
+/// This struct models the optional project configuration file, `project.yaml`.
+/// It holds settings that affect the whole release notes project.
+/// If the file is missing, all settings use their default values.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub appendix: Appendix,
+    pub status: StatusRules,
+    /// Output formats that acorns generates in addition to AsciiDoc.
+    pub formats: Vec<OutputFormat>,
+    pub titles: Titles,
+    /// Document variants that acorns generates in addition to the internal and external variants.
+    pub variants: Vec<Variant>,
+}
+
+/// A document variant that the project defines in addition to the built-in internal
+/// and external variants.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Variant {
+    /// The name of the variant, which also names its files in the additional output formats.
+    pub name: String,
+    /// The output subdirectory under `generated/`. The default is the name.
+    pub directory: Option<String>,
+    /// The variant only includes the tickets that match the filter.
+    #[serde(default)]
+    pub filter: Filter,
+    /// Include tickets whose doc text isn't approved yet.
+    #[serde(default)]
+    pub include_unapproved: bool,
+    /// Add the line with the docs contact, the doc text status, and the ticket link
+    /// to every release note, as in the internal variant.
+    #[serde(default)]
+    pub debug_info: bool,
+}
+
+impl Variant {
+    /// A built-in variant. The internal variant includes all tickets with the debugging
+    /// information, and the external variant only includes tickets with an approved doc text.
+    pub fn builtin(name: &str, internal: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            directory: None,
+            filter: Filter::default(),
+            include_unapproved: internal,
+            debug_info: internal,
+        }
+    }
+
+    /// The output subdirectory of the variant under `generated/`.
+    pub fn directory(&self) -> &str {
+        self.directory.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Names and directories that the generated files of the built-in variants
+/// and of the additional output formats already use.
+const RESERVED_VARIANT_NAMES: [&str; 6] = [
+    "internal",
+    "external",
+    "markdown",
+    "json",
+    "docbook",
+    "translations",
+];
+
+/// Check that the names and the output directories of the variants are unique,
+/// and that they can safely become parts of file names.
+fn validate_variants(variants: &[Variant]) -> Result<()> {
+    let mut used: Vec<&str> = RESERVED_VARIANT_NAMES.to_vec();
+
+    for variant in variants {
+        for value in [variant.name.as_str(), variant.directory()] {
+            if value.is_empty()
+                || !value
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                bail!(
+                    "Invalid name or directory of the `{}` variant: {value}",
+                    variant.name
+                );
+            }
+        }
+
+        if used.contains(&variant.name.as_str()) {
+            bail!("The variant name `{}` is already in use.", variant.name);
+        }
+        used.push(&variant.name);
+
+        // A directory that's the same as the name of the variant is already recorded.
+        let directory = variant.directory();
+        if directory != variant.name {
+            if used.contains(&directory) {
+                bail!("The variant directory `{directory}` is already in use.");
+            }
+            used.push(directory);
+        }
+    }
+
+    Ok(())
+}
+
+/// The configuration of titles that acorns generates for release notes without a title.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Titles {
+    /// Use the ticket summary as the title if the doc text has none.
+    pub from_summary: bool,
+    /// A regular expression that removes parts of the summary, such as an `[RFE]` prefix.
+    pub cleanup: Option<Cleanup>,
+}
+
+/// A regular expression that removes all its matches from a text.
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Cleanup(Regex);
+
+impl TryFrom<String> for Cleanup {
+    type Error = color_eyre::eyre::Error;
+
+    fn try_from(source: String) -> Result<Self> {
+        let regex = Regex::new(&source)
+            .wrap_err_with(|| format!("Invalid regular expression in title cleanup: {source}"))?;
+
+        Ok(Self(regex))
+    }
+}
+
+impl Cleanup {
+    /// Remove all matches from the text, and the surrounding white space.
+    pub fn apply(&self, text: &str) -> String {
+        self.0.replace_all(text, "").trim().to_string()
+    }
+}
+
+impl PartialEq for Cleanup {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+/// An additional output format of the generated release notes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// CommonMark files, one per chapter.
+    Markdown,
+    /// A standalone HTML page for each variant.
+    Html,
+    /// A DocBook 5 book for each variant.
+    Docbook,
+    /// The structure of each variant, with all its release notes, as JSON.
+    Json,
+    /// A PO template with all translatable texts, for a translated build.
+    Po,
+}
+
+/// The configuration of the appendix that lists all tickets by component.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Appendix {
+    /// Components that only categorize tickets internally.
+    pub internal_components: Vec<String>,
+    /// Prefixes shared by other internal components.
+    pub internal_prefixes: Vec<String>,
+    /// The placeholder heading that replaces all internal components.
+    pub internal_placeholder: String,
+    /// Display names that replace component names, so that several
+    /// components can appear under one heading.
+    pub aliases: HashMap<String, String>,
+}
+
+impl Default for Appendix {
+    fn default() -> Self {
+        Self {
+            internal_components: vec![
+                "releng".to_string(),
+                "(none)".to_string(),
+                "Documentation".to_string(),
+            ],
+            internal_prefixes: vec![
+                "doc-".to_string(),
+                "Red_Hat_Enterprise_Linux-Release_Notes".to_string(),
+            ],
+            internal_placeholder: "other".to_string(),
+            aliases: HashMap::new(),
+        }
+    }
+}
+
+/// The configuration of the checks that the status table performs on each ticket.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusRules {
+    /// The maximum allowed title length for a release note, in characters.
+    pub max_title_length: usize,
+    /// Doc type values that only stand in for a missing doc type.
+    pub placeholder_doc_types: Vec<String>,
+    /// These doc types don't belong to any particular target release.
+    /// Skip the release check for these.
+    pub unchecked_doc_types: Vec<String>,
+    /// The severity of each check.
+    pub checks: CheckLevels,
+}
+
+impl Default for StatusRules {
+    fn default() -> Self {
+        Self {
+            max_title_length: 120,
+            placeholder_doc_types: vec!["If docs needed, set a value".to_string()],
+            unchecked_doc_types: vec![
+                "Known Issue".to_string(),
+                "Technology Preview".to_string(),
+                "Deprecated Functionality".to_string(),
+            ],
+            checks: CheckLevels::default(),
+        }
+    }
+}
+
+/// The severity of each check in the status table.
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CheckLevels {
+    /// The ticket is in early stages of development.
+    pub development: CheckLevel,
+    /// The doc type is a placeholder.
+    pub doc_type: CheckLevel,
+    /// The release note isn't approved.
+    pub doc_status: CheckLevel,
+    /// The doc text is empty, or it lacks a proper title.
+    pub text: CheckLevel,
+    /// The release note title is too long.
+    pub title_length: CheckLevel,
+    /// The ticket targets a different release than most tickets.
+    pub target_release: CheckLevel,
+}
+
+impl Default for CheckLevels {
+    fn default() -> Self {
+        Self {
+            development: CheckLevel::Warning,
+            doc_type: CheckLevel::Error,
+            doc_status: CheckLevel::Error,
+            text: CheckLevel::Error,
+            title_length: CheckLevel::Warning,
+            target_release: CheckLevel::Warning,
+        }
+    }
+}
+
+/// How seriously the status table treats a failed check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckLevel {
+    Off,
+    Warning,
+    Error,
+}
+
+/// A value in a filter property. It can take one of the following forms:
+///
+/// * A plain string, which matches the ticket value exactly.
+/// * A regular expression with the `re:` prefix.
+/// * A glob with the `glob:` prefix.
+///
+/// All forms ignore letter case. Regular expressions and globs compile
+/// once, when the configuration loads, so that invalid patterns fail early.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Pattern {
+    /// The pattern as written in the configuration file.
+    source: String,
+    matcher: Matcher,
+}
+
+/// The compiled form of a `Pattern`.
+#[derive(Clone, Debug)]
+enum Matcher {
+    /// A lower-case string.
+    Exact(String),
+    Regex(Regex),
+    Glob(GlobMatcher),
+}
+
+impl TryFrom<String> for Pattern {
+    type Error = color_eyre::eyre::Error;
+
+    fn try_from(source: String) -> Result<Self> {
+        let matcher = if let Some(regex) = source.strip_prefix("re:") {
+            let regex = RegexBuilder::new(regex)
+                .case_insensitive(true)
+                .build()
+                .wrap_err_with(|| format!("Invalid regular expression in a filter: {source}"))?;
+            Matcher::Regex(regex)
+        } else if let Some(glob) = source.strip_prefix("glob:") {
+            let glob = GlobBuilder::new(glob)
+                .case_insensitive(true)
+                .build()
+                .wrap_err_with(|| format!("Invalid glob in a filter: {source}"))?;
+            Matcher::Glob(glob.compile_matcher())
+        } else {
+            Matcher::Exact(source.to_lowercase())
+        };
+
+        Ok(Self { source, matcher })
+    }
+}
+
+impl Pattern {
+    /// A pattern that matches exactly this value, even if it looks like a regular expression or a glob.
+    pub fn exact(value: &str) -> Self {
+        Self {
+            source: value.to_string(),
+            matcher: Matcher::Exact(value.to_lowercase()),
+        }
+    }
+
+    /// Checks whether the pattern matches a ticket value, regardless of letter case.
+    pub fn is_match(&self, value: &str) -> bool {
+        match &self.matcher {
+            Matcher::Exact(string) => string == &value.to_lowercase(),
+            Matcher::Regex(regex) => regex.is_match(value),
+            Matcher::Glob(glob) => glob.is_match(value),
+        }
+    }
+}
+
+// Compiled patterns can't be compared. Compare the configured strings instead.
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for Pattern {}
+
+/// A ticket property that orders release notes within a section.
+///
+/// The configured form is the property name, optionally followed by
+/// the `asc` or `desc` direction, such as `priority desc`.
+/// Several keys chain: later keys only order tickets that are equal in the earlier ones.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct SortKey {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+/// The ticket properties that can order release notes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SortField {
+    /// The first component of the ticket.
+    Component,
+    /// From the least to the most severe priority. Unknown priorities are the least severe.
+    Priority,
+    Summary,
+    /// The ticket key, with numbers compared by value.
+    Key,
+    DocType,
+}
+
+impl TryFrom<String> for SortKey {
+    type Error = color_eyre::eyre::Error;
+
+    fn try_from(source: String) -> Result<Self> {
+        let mut words = source.split_whitespace();
+
+        let field = match words.next().map(str::to_lowercase).as_deref() {
+            Some("component") => SortField::Component,
+            Some("priority") => SortField::Priority,
+            Some("summary") => SortField::Summary,
+            Some("key") => SortField::Key,
+            Some("doc_type") => SortField::DocType,
+            _ => bail!("Invalid sort key: {source}"),
+        };
+
+        let descending = match words.next().map(str::to_lowercase).as_deref() {
+            None | Some("asc") => false,
+            Some("desc") => true,
+            Some(_) => bail!("Invalid sort direction: {source}"),
+        };
+
+        if words.next().is_some() {
+            bail!("Invalid sort key: {source}");
+        }
+
+        Ok(Self { field, descending })
+    }
 }
 
 /// Parse the specified tickets config file into the ticket queries configuration.
@@ -411,20 +1020,45 @@ fn parse_trackers(trackers_file: &Path) -> Result<tracker::Config> {
 /// Parse the template configuration files into template structs, with chapter and section definitions.
 fn parse_templates(template_file: &Path) -> Result<Template> {
     let text = fs::read_to_string(template_file).wrap_err("Cannot read the template file.")?;
-    let templates: Template =
+    let mut templates: Template =
         serde_yaml::from_str(&text).wrap_err("Cannot parse the template file.")?;
+    templates
+        .resolve_reused_sections()
+        .wrap_err("Invalid reusable sections in the template file.")?;
     log::debug!("{:#?}", templates);
     Ok(templates)
 }
 
+/// Parse the optional project settings file. If the file doesn't exist, use the default settings.
+fn parse_settings(settings_file: &Path) -> Result<Settings> {
+    if !settings_file.exists() {
+        log::debug!("No project settings file. Using the default settings.");
+        return Ok(Settings::default());
+    }
+
+    let text =
+        fs::read_to_string(settings_file).wrap_err("Cannot read the project settings file.")?;
+    let settings: Settings =
+        serde_yaml::from_str(&text).wrap_err("Cannot parse the project settings file.")?;
+    validate_variants(&settings.variants)
+        .wrap_err("Invalid variants in the project settings file.")?;
+    log::debug!("{:#?}", settings);
+
+    Ok(settings)
+}
+
 /// Parsed input metadata that represent the configuration of a release notes project
 pub struct Project {
     pub _base_dir: PathBuf,
     pub generated_dir: PathBuf,
+    pub translations_dir: PathBuf,
     pub tickets: Vec<Arc<TicketQuery>>,
     pub trackers: tracker::Config,
     pub templates: Template,
-    pub private_footnote: bool,
+    pub settings: Settings,
+    pub custom_templates: CustomTemplates,
+    /// The text of the private ticket footnote, if the project defines the footnote.
+    pub private_footnote: Option<String>,
 }
 
 impl Project {
@@ -434,18 +1068,22 @@ impl Project {
         let abs_path = directory.canonicalize()?;
         let data_dir = locate_data_dir(directory)?;
         let generated_dir = data_dir.join(GENERATED_PREFIX);
+        let translations_dir = data_dir.join("translations");
 
         // Prepare to access each configuration file.
         // TODO: Possibly enable overriding the default config paths.
         let tickets_path = data_dir.join("tickets.yaml");
         let trackers_path = data_dir.join("trackers.yaml");
         let templates_path = data_dir.join("templates.yaml");
+        let settings_path = data_dir.join("project.yaml");
+        let custom_templates_dir = data_dir.join("templates");
 
         log::debug!(
-            "Configuration files:\n* {}\n* {}\n* {}",
+            "Configuration files:\n* {}\n* {}\n* {}\n* {}",
             tickets_path.display(),
             trackers_path.display(),
-            templates_path.display()
+            templates_path.display(),
+            settings_path.display()
         );
 
         let tickets = parse_tickets(&tickets_path)?
@@ -454,20 +1092,32 @@ impl Project {
             .collect();
         let trackers = parse_trackers(&trackers_path)?;
         let templates = parse_templates(&templates_path)?;
+        let settings = parse_settings(&settings_path)?;
+        let custom_templates = CustomTemplates::load(&custom_templates_dir)?;
 
         log::info!("Valid release notes project in {}.", abs_path.display());
 
-        let private_footnote = footnote::is_footnote_defined(&abs_path)?;
+        let private_footnote = footnote::defined_footnote(&abs_path)?;
 
         Ok(Self {
             _base_dir: abs_path,
             generated_dir,
+            translations_dir,
             tickets,
             trackers,
             templates,
+            settings,
+            custom_templates,
             private_footnote,
         })
     }
+
+    /// The output directory of a translated build. It's next to the regular output directory,
+    /// so that the regular build doesn't remove it.
+    pub fn localized_dir(&self, lang: &str) -> PathBuf {
+        self.generated_dir
+            .with_file_name(format!("{GENERATED_PREFIX}-{lang}"))
+    }
 }
 
 /// Find the base data and configuration directory.
@@ -492,6 +1142,10 @@ fn locate_data_dir(directory: &Path) -> Result<PathBuf> {
                 DATA_PREFIX
             );
             log::warn!("After renaming, you also have to adjust AsciiDoc include paths.");
+            log::warn!(
+                "The `{} migrate` command can do both for you.",
+                PROGRAM_NAME
+            );
             Ok(legacy_data_dir)
         } else {
             // If the legacy directory doesn't exist either, return an error.
@@ -502,3 +1156,124 @@ fn locate_data_dir(directory: &Path) -> Result<PathBuf> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_subheadings_in_assembly() {
+        let mut template: Template = serde_yaml::from_str(
+            r#"
+            chapters:
+              - title: "Bug fixes"
+                subheadings: subsystem
+                subsections:
+                  - title: "Kernel"
+            "#,
+        )
+        .expect("Invalid template in a test.");
+        assert!(template.resolve_reused_sections().is_err());
+    }
+
+    #[test]
+    fn validate_variant_names() {
+        let variants = |yaml: &str| -> Vec<Variant> {
+            serde_yaml::from_str(yaml).expect("Invalid variants in a test.")
+        };
+
+        assert!(validate_variants(&variants(
+            r"
+            - name: partner-preview
+            - name: portal
+              directory: customer_portal
+            "
+        ))
+        .is_ok());
+        // The built-in variants already use these names.
+        assert!(validate_variants(&variants("- name: external")).is_err());
+        assert!(validate_variants(&variants(
+            r"
+            - name: portal
+            - name: preview
+              directory: portal
+            "
+        ))
+        .is_err());
+        // The directory must stay under `generated/`.
+        assert!(validate_variants(&variants(
+            r"
+            - name: portal
+              directory: ../portal
+            "
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn reuse_named_sections() {
+        let mut template: Template = serde_yaml::from_str(
+            r#"
+            subsections:
+              - name: by-subsystem
+                title: "Networking"
+                filter:
+                  subsystem: ["sst_networking"]
+                subsections:
+                  - use: kernel
+              - name: kernel
+                title: "Kernel"
+                filter:
+                  component: ["kernel"]
+                catch_all: true
+            chapters:
+              - title: "Bug fixes"
+                subsections:
+                  - use: by-subsystem
+              - title: "Known issues"
+                subsections:
+                  - use: by-subsystem
+                    title: "Network issues"
+                    filter:
+                      subsystem: ["sst_network_drivers"]
+              - title: "Enhancements"
+                subsections:
+                  - use: kernel
+                    catch_all: false
+            "#,
+        )
+        .expect("Invalid template in a test.");
+        template
+            .resolve_reused_sections()
+            .expect("Failed to resolve reusable sections.");
+
+        let subsection = |chapter: &Section| -> Section {
+            chapter.subsections.as_ref().expect("No subsections.")[0].clone()
+        };
+
+        let bug_fixes = subsection(&template.chapters[0]);
+        assert_eq!(bug_fixes.title, "Networking");
+        assert_eq!(subsection(&bug_fixes).title, "Kernel");
+
+        let known_issues = subsection(&template.chapters[1]);
+        assert_eq!(known_issues.title, "Network issues");
+        assert_ne!(known_issues.filter, bug_fixes.filter);
+        assert_eq!(known_issues.subsections, bug_fixes.subsections);
+
+        // A section can turn off a property that the reusable section turns on.
+        assert_eq!(subsection(&bug_fixes).catch_all, Some(true));
+        assert_eq!(subsection(&template.chapters[2]).catch_all, Some(false));
+
+        let mut cycle: Template = serde_yaml::from_str(
+            r"
+            subsections:
+              - name: loop
+                use: loop
+            chapters:
+              - use: loop
+            ",
+        )
+        .expect("Invalid template in a test.");
+        assert!(cycle.resolve_reused_sections().is_err());
+    }
+}
@@ -0,0 +1,139 @@
+/*
+acorns: Generate an AsciiDoc release notes document from tracking tickets.
+Copyright (C) 2023  Marek Suchánek  <msuchane@redhat.com>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Output templates that a release notes project can override at runtime.
+//!
+//! By default, acorns renders its output with templates that are built into the binary.
+//! If the `acorns/templates/` directory in the project contains a file with the same name
+//! as a built-in template, acorns renders that output with the project file instead,
+//! using the Jinja syntax. Each template receives the variables of its built-in counterpart,
+//! as documented in `docs/modules/ref_custom-output-templates.adoc`.
+
+use std::fs;
+use std::path::Path;
+
+use color_eyre::eyre::{Result, WrapErr};
+use minijinja::Environment;
+use serde::Serialize;
+
+/// The reference module template for a leaf section.
+pub const REFERENCE: &str = "reference.adoc";
+/// The assembly template for a section that includes subsections.
+pub const ASSEMBLY: &str = "assembly.adoc";
+/// The appendix that lists tickets by component.
+pub const SUMMARY_LIST: &str = "summary-list.adoc";
+/// The HTML status table.
+pub const STATUS_TABLE: &str = "status-table.html";
+/// The standalone HTML preview of a document variant.
+pub const PREVIEW: &str = "preview.html";
+
+/// All templates that a project can override.
+const OVERRIDABLE: [&str; 5] = [REFERENCE, ASSEMBLY, SUMMARY_LIST, STATUS_TABLE, PREVIEW];
+
+/// The templates that the project overrides, loaded from the project directory.
+/// Empty if the project uses only the built-in templates.
+#[derive(Debug, Default)]
+pub struct CustomTemplates {
+    env: Environment<'static>,
+}
+
+impl CustomTemplates {
+    /// Load all overriding templates from the templates directory.
+    /// The directory is optional. Checks the syntax of each template early,
+    /// so that a broken template fails before acorns downloads any tickets.
+    pub fn load(templates_dir: &Path) -> Result<Self> {
+        // Like the built-in templates, drop the final line break of the template.
+        let mut env = Environment::new();
+
+        for name in OVERRIDABLE {
+            let path = templates_dir.join(name);
+
+            if path.is_file() {
+                log::info!("Using the custom template: {}", path.display());
+                let source = fs::read_to_string(&path)
+                    .wrap_err_with(|| format!("Failed to read the template: {}", path.display()))?;
+                env.add_template_owned(name, source)
+                    .wrap_err_with(|| format!("Invalid template: {}", path.display()))?;
+            }
+        }
+
+        Ok(Self { env })
+    }
+
+    /// Render the overriding template of this name with the variables in `context`.
+    /// Returns `None` if the project doesn't override this template.
+    pub fn render_custom<S: Serialize>(&self, name: &str, context: S) -> Option<Result<String>> {
+        let template = self.env.get_template(name).ok()?;
+
+        Some(
+            template
+                .render(context)
+                .wrap_err_with(|| format!("Failed to render the custom template: {name}")),
+        )
+    }
+
+    /// Render the overriding template of this name if the project has one,
+    /// passing it the fields of the built-in template as variables.
+    /// Otherwise, render the built-in template.
+    pub fn render<T: askama::Template + Serialize>(
+        &self,
+        name: &str,
+        template: &T,
+    ) -> Result<String> {
+        match self.render_custom(name, template) {
+            Some(result) => result,
+            None => template
+                .render()
+                .wrap_err_with(|| format!("Failed to render the built-in template: {name}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn override_templates() {
+        let dir = std::env::temp_dir().join(format!("acorns-templates-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("Failed to create a test directory.");
+        fs::write(
+            dir.join(REFERENCE),
+            "== {{ title }}\n{% for note in release_notes %}{{ note }}\n{% endfor %}",
+        )
+        .expect("Failed to write a test template.");
+
+        let templates = CustomTemplates::load(&dir).expect("Failed to load the templates.");
+        let context = serde_json::json!({
+            "title": "Bug fixes",
+            "release_notes": ["First", "Second"],
+        });
+
+        let rendered = templates
+            .render_custom(REFERENCE, &context)
+            .expect("The template isn't overridden.")
+            .expect("Failed to render the template.");
+        assert_eq!(rendered, "== Bug fixes\nFirst\nSecond\n");
+        assert!(templates.render_custom(ASSEMBLY, &context).is_none());
+
+        fs::write(dir.join(ASSEMBLY), "{% for %}").expect("Failed to write a test template.");
+        assert!(CustomTemplates::load(&dir).is_err());
+
+        fs::remove_dir_all(&dir).expect("Failed to remove a test directory.");
+    }
+}
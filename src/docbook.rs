@@ -0,0 +1,661 @@
+/*
+acorns: Generate an AsciiDoc release notes document from tracking tickets.
+Copyright (C) 2023  Marek Suchánek  <msuchane@redhat.com>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! A renderer that converts a document variant to a DocBook 5 book.
+//!
+//! The renderer converts the generated modules to Markdown first, and maps the Markdown
+//! structure to DocBook. The top-level sections become chapters, nested sections become
+//! sections, and each release note becomes a section with the anchor of its ticket.
+//! Therefore, the book supports the same subset of AsciiDoc as the Markdown output.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use color_eyre::eyre::{Result, WrapErr};
+use once_cell::sync::Lazy;
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+
+use crate::markdown;
+use crate::templating::Module;
+use crate::REGEX_ERROR;
+
+/// The start of the book, up to the first chapter.
+const BOOK_START: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<book xmlns="http://docbook.org/ns/docbook" xmlns:xlink="http://www.w3.org/1999/xlink" version="5.0">
+<info>
+<title>Release notes</title>
+</info>
+"#;
+
+/// The anchor that the Markdown conversion places before a heading or a release note.
+static ANCHOR_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^<a id="([^"]+)">"#).expect(REGEX_ERROR));
+
+/// The anchor of a release note, as `AbstractTicket::copy_anchor` forms it.
+/// For example, `BZ-12345` or `Jira-RHEL-123_known-issues`.
+static NOTE_ANCHOR_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:BZ|Jira)-[^_]+(?:_.+)?$").expect(REGEX_ERROR));
+
+/// A complete HTML comment that is also a valid XML comment, such as the mark
+/// of unconverted AsciiDoc. XML comments can't contain `--`, or end with `-`.
+static COMMENT_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^<!--(?:[^-]|-[^-])*-->\s*$").expect(REGEX_ERROR));
+
+/// The labels of admonitions, as the Markdown conversion writes them, with their DocBook elements.
+const ADMONITIONS: [(&str, &str); 5] = [
+    ("Note:", "note"),
+    ("Tip:", "tip"),
+    ("Important:", "important"),
+    ("Warning:", "warning"),
+    ("Caution:", "caution"),
+];
+
+/// Convert the modules and the summary appendix of a document variant to DocBook,
+/// and write the book to the output file.
+pub fn write_variant(
+    modules: &[Module],
+    summary: &str,
+    private_footnote: Option<&str>,
+    out_file: &Path,
+) -> Result<()> {
+    let file_name = out_file
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+
+    if let Some(dir) = out_file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    log::debug!("Writing file: {}", out_file.display());
+    fs::write(
+        out_file,
+        book(modules, summary, private_footnote, &file_name),
+    )
+    .wrap_err("Failed to write a generated DocBook file.")
+}
+
+/// Convert a document variant to a DocBook book, with the summary as an appendix.
+fn book(
+    modules: &[Module],
+    summary: &str,
+    private_footnote: Option<&str>,
+    file_name: &str,
+) -> String {
+    let (chapters, appendix) =
+        markdown::single_page_parts(modules, summary, private_footnote, file_name);
+
+    let mut book = String::from(BOOK_START);
+    book.push_str(&Writer::new("chapter").convert(&chapters));
+    book.push_str(&Writer::new("appendix").convert(&appendix));
+    book.push_str("</book>\n");
+
+    book
+}
+
+/// Escape the characters that XML reserves.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The conversion of Markdown events to DocBook elements.
+struct Writer {
+    /// The element of a top-level section: a chapter or an appendix.
+    top_level: &'static str,
+    /// The converted DocBook, in fragments.
+    out: Vec<String>,
+    /// The levels of the sections that are currently open.
+    open_sections: Vec<usize>,
+    /// The level of the last heading, which release notes nest under.
+    heading_level: usize,
+    /// The anchor that the next element takes as its ID.
+    pending_id: Option<String>,
+    /// The converted text of each footnote, by label.
+    footnotes: HashMap<String, String>,
+    /// The footnotes that already appeared once. Later references only point to them.
+    used_footnotes: HashSet<String>,
+    /// The closing tags of the open list items and block quotes.
+    closing_tags: Vec<&'static str>,
+}
+
+impl Writer {
+    fn new(top_level: &'static str) -> Self {
+        Self {
+            top_level,
+            out: Vec::new(),
+            open_sections: Vec::new(),
+            heading_level: 0,
+            pending_id: None,
+            footnotes: HashMap::new(),
+            used_footnotes: HashSet::new(),
+            closing_tags: Vec::new(),
+        }
+    }
+
+    /// Convert a Markdown document to a sequence of DocBook chapters or appendices.
+    fn convert(mut self, markdown: &str) -> String {
+        let options = Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES;
+        let events: Vec<Event> = Parser::new_ext(markdown, options).collect();
+
+        // DocBook places the footnote text at the reference, so convert the definitions first.
+        let mut main = Vec::new();
+        let mut events = events.into_iter();
+        while let Some(event) = events.next() {
+            if let Event::Start(Tag::FootnoteDefinition(label)) = event {
+                let definition: Vec<Event> = events
+                    .by_ref()
+                    .take_while(|e| !matches!(e, Event::End(TagEnd::FootnoteDefinition)))
+                    .collect();
+                let mut writer = Self::new(self.top_level);
+                writer.events(&definition);
+                self.footnotes
+                    .insert(label.to_string(), writer.out.concat());
+            } else {
+                main.push(event);
+            }
+        }
+
+        self.events(&main);
+        self.close_sections(1);
+
+        self.out.concat()
+    }
+
+    fn push(&mut self, text: impl Into<String>) {
+        self.out.push(text.into());
+    }
+
+    /// The DocBook element of a section at this level.
+    fn element(&self, level: usize) -> &'static str {
+        if level == 1 {
+            self.top_level
+        } else {
+            "section"
+        }
+    }
+
+    /// The ID attribute from the pending anchor, if any.
+    fn id_attribute(&mut self) -> String {
+        self.pending_id
+            .take()
+            .map_or_else(String::new, |id| format!(" xml:id=\"{}\"", escape(&id)))
+    }
+
+    /// Close all open sections at this level and deeper.
+    fn close_sections(&mut self, level: usize) {
+        while self.open_sections.last().is_some_and(|open| *open >= level) {
+            if let Some(open) = self.open_sections.pop() {
+                let element = self.element(open);
+                self.push(format!("</{element}>\n"));
+            }
+        }
+    }
+
+    /// Open a section at this level, after closing the sections that it follows.
+    fn open_section(&mut self, level: usize) {
+        self.close_sections(level);
+        let element = self.element(level);
+        let id = self.id_attribute();
+        self.push(format!("<{element}{id}>\n<title>"));
+        self.open_sections.push(level);
+    }
+
+    /// Open the section of a release note that has no title. DocBook requires a title,
+    /// so the section has an empty one, and a role that stylesheets can use to hide it.
+    fn open_untitled_note(&mut self) {
+        let level = self.heading_level + 1;
+        self.close_sections(level);
+        let element = self.element(level);
+        let id = self.id_attribute();
+        self.push(format!("<{element}{id} role=\"untitled\">\n<title/>\n"));
+        self.open_sections.push(level);
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn events(&mut self, events: &[Event]) {
+        let mut index = 0;
+
+        while index < events.len() {
+            match &events[index] {
+                Event::Start(Tag::Paragraph) => {
+                    if let Some(skip) = self.anchor_paragraph(&events[index..]) {
+                        index += skip;
+                        continue;
+                    }
+                    if let Some(end) = self.release_note_title(&events[index..]) {
+                        // The title of a release note, between the strong tags.
+                        self.open_section(self.heading_level + 1);
+                        self.events(&events[index + 2..index + end]);
+                        self.push("</title>\n");
+                        index += end + 2;
+                        continue;
+                    }
+                    let id = self.id_attribute();
+                    self.push(format!("<para{id}>"));
+                }
+                Event::End(TagEnd::Paragraph) => self.push("</para>\n"),
+                Event::Start(Tag::Heading { level, .. }) => {
+                    let level = *level as usize;
+                    self.heading_level = level;
+                    self.open_section(level);
+                }
+                Event::End(TagEnd::Heading(_)) => self.push("</title>\n"),
+                Event::Start(Tag::BlockQuote(_)) => {
+                    if let Some((element, label_end)) = admonition(&events[index..]) {
+                        let id = self.id_attribute();
+                        self.push(format!("<{element}{id}>\n"));
+                        self.closing_tags.push(element);
+                        // Skip the label.
+                        index += label_end + 1;
+                        if matches!(events.get(index), Some(Event::End(TagEnd::Paragraph))) {
+                            // The label stands alone in its paragraph, so skip the paragraph, too.
+                            index += 1;
+                        } else {
+                            // The label starts the first paragraph, which continues here.
+                            self.push("<para>");
+                            if let Some(Event::Text(text)) = events.get(index) {
+                                self.push(escape(text.trim_start()));
+                                index += 1;
+                            }
+                        }
+                        continue;
+                    }
+                    let id = self.id_attribute();
+                    self.push(format!("<blockquote{id}>\n"));
+                    self.closing_tags.push("blockquote");
+                }
+                Event::End(TagEnd::BlockQuote(_)) => {
+                    let element = self.closing_tags.pop().unwrap_or("blockquote");
+                    self.push(format!("</{element}>\n"));
+                }
+                Event::Start(Tag::CodeBlock(kind)) => {
+                    let id = self.id_attribute();
+                    match kind {
+                        CodeBlockKind::Fenced(language) if !language.is_empty() => {
+                            self.push(format!(
+                                "<programlisting{id} language=\"{}\">",
+                                escape(language)
+                            ));
+                        }
+                        _ => self.push(format!("<programlisting{id}>")),
+                    }
+                }
+                Event::End(TagEnd::CodeBlock) => self.push("</programlisting>\n"),
+                Event::Start(Tag::List(start)) => {
+                    let id = self.id_attribute();
+                    let element = if start.is_some() {
+                        "orderedlist"
+                    } else {
+                        "itemizedlist"
+                    };
+                    self.push(format!("<{element}{id}>\n"));
+                }
+                Event::End(TagEnd::List(ordered)) => {
+                    let element = if *ordered {
+                        "orderedlist"
+                    } else {
+                        "itemizedlist"
+                    };
+                    self.push(format!("</{element}>\n"));
+                }
+                Event::Start(Tag::Item) => {
+                    // Items of a tight list contain text without a paragraph.
+                    if matches!(events.get(index + 1), Some(Event::Start(Tag::Paragraph))) {
+                        self.push("<listitem>\n");
+                        self.closing_tags.push("</listitem>\n");
+                    } else {
+                        self.push("<listitem><para>");
+                        self.closing_tags.push("</para></listitem>\n");
+                    }
+                }
+                Event::End(TagEnd::Item) => {
+                    let closing = self.closing_tags.pop().unwrap_or("</listitem>\n");
+                    self.push(closing);
+                }
+                Event::Start(Tag::Table(alignments)) => {
+                    let id = self.id_attribute();
+                    self.push(format!(
+                        "<informaltable{id}>\n<tgroup cols=\"{}\">\n",
+                        alignments.len()
+                    ));
+                }
+                Event::End(TagEnd::Table) => {
+                    self.push("</tbody>\n</tgroup>\n</informaltable>\n");
+                }
+                Event::Start(Tag::TableHead) => self.push("<thead>\n<row>\n"),
+                Event::End(TagEnd::TableHead) => self.push("</row>\n</thead>\n<tbody>\n"),
+                Event::Start(Tag::TableRow) => self.push("<row>\n"),
+                Event::End(TagEnd::TableRow) => self.push("</row>\n"),
+                Event::Start(Tag::TableCell) => self.push("<entry>"),
+                Event::End(TagEnd::TableCell) => self.push("</entry>\n"),
+                Event::Start(Tag::Emphasis) => self.push("<emphasis>"),
+                Event::Start(Tag::Strong) => self.push("<emphasis role=\"strong\">"),
+                Event::End(TagEnd::Emphasis | TagEnd::Strong) => self.push("</emphasis>"),
+                Event::Start(Tag::Link { dest_url, .. }) => {
+                    if let Some(id) = dest_url.strip_prefix('#') {
+                        self.push(format!("<link linkend=\"{}\">", escape(id)));
+                    } else {
+                        self.push(format!("<link xlink:href=\"{}\">", escape(dest_url)));
+                    }
+                }
+                Event::End(TagEnd::Link) => self.push("</link>"),
+                Event::Text(text) => self.push(escape(text)),
+                Event::Code(code) => {
+                    self.push(format!("<literal>{}</literal>", escape(code)));
+                }
+                Event::SoftBreak => self.push("\n"),
+                // The same processing instruction that Asciidoctor uses for a hard line break.
+                Event::HardBreak => self.push("<?asciidoc-br?>\n"),
+                Event::FootnoteReference(label) => self.footnote_reference(label),
+                Event::Html(html) | Event::InlineHtml(html) => {
+                    if let Some(caps) = ANCHOR_REGEX.captures(html) {
+                        self.push(format!("<anchor xml:id=\"{}\"/>", escape(&caps[1])));
+                    } else if COMMENT_REGEX.is_match(html) {
+                        self.push(html.as_ref());
+                    } else if !html.starts_with("</a>") {
+                        self.push(escape(html));
+                    }
+                }
+                _ => {}
+            }
+
+            index += 1;
+        }
+    }
+
+    /// If the paragraph only holds an anchor, record the anchor for the next element.
+    /// Returns the number of events that the paragraph spans.
+    ///
+    /// The anchor of a release note also ends the previous release note. If the release note
+    /// has no title, its section starts right away.
+    fn anchor_paragraph(&mut self, events: &[Event]) -> Option<usize> {
+        match events {
+            [Event::Start(Tag::Paragraph), Event::InlineHtml(html), Event::InlineHtml(_), Event::End(TagEnd::Paragraph), rest @ ..] =>
+            {
+                let caps = ANCHOR_REGEX.captures(html)?;
+                let id = caps[1].to_string();
+                let is_note = self.closing_tags.is_empty() && NOTE_ANCHOR_REGEX.is_match(&id);
+                self.pending_id = Some(id);
+
+                if is_note {
+                    self.close_sections(self.heading_level + 1);
+                    if self.release_note_title(rest).is_none() {
+                        self.open_untitled_note();
+                    }
+                }
+                Some(4)
+            }
+            _ => None,
+        }
+    }
+
+    /// If the paragraph is entirely strong text and follows an anchor, it's the title
+    /// of a release note. Returns the index of the end of the strong text.
+    fn release_note_title(&self, events: &[Event]) -> Option<usize> {
+        self.pending_id.as_ref()?;
+
+        if !matches!(events.get(1), Some(Event::Start(Tag::Strong))) {
+            return None;
+        }
+        let end = events
+            .iter()
+            .position(|e| matches!(e, Event::End(TagEnd::Strong)))?;
+
+        matches!(events.get(end + 1), Some(Event::End(TagEnd::Paragraph))).then_some(end)
+    }
+
+    /// Place the footnote text at its first reference, and point to it from later references.
+    fn footnote_reference(&mut self, label: &str) {
+        let id = format!("footnote-{}", escape(label));
+
+        if self.used_footnotes.contains(label) {
+            self.push(format!("<footnoteref linkend=\"{id}\"/>"));
+        } else if let Some(text) = self.footnotes.get(label) {
+            self.push(format!(
+                "<footnote xml:id=\"{id}\">{}</footnote>",
+                text.trim_end()
+            ));
+            self.used_footnotes.insert(label.to_string());
+        } else {
+            self.push(escape(&format!("[^{label}]")));
+        }
+    }
+}
+
+/// If the block quote starts with an admonition label, returns the DocBook element
+/// of the admonition, and the index of the event that ends the label.
+fn admonition(events: &[Event]) -> Option<(&'static str, usize)> {
+    let [Event::Start(Tag::BlockQuote(_)), Event::Start(Tag::Paragraph), Event::Start(Tag::Strong), Event::Text(label), ..] =
+        events
+    else {
+        return None;
+    };
+    let (_, element) = ADMONITIONS
+        .iter()
+        .find(|(known, _)| known == &label.as_ref())?;
+
+    let label_end = events
+        .iter()
+        .position(|e| matches!(e, Event::End(TagEnd::Strong)))?;
+
+    Some((element, label_end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::tests::PRIVATE_FOOTNOTE;
+
+    #[test]
+    fn convert_to_docbook() {
+        let reference = Module::WithContent {
+            file_name: "ref_bug-fixes-kernel.adoc".into(),
+            text: r#"[id="bug-fixes-kernel"]
+= Kernel
+
+[id="Jira-A-1"]
+.A fixed bug
+
+The `+--force+` option *no longer* fails.
+
+NOTE: Reboot.
+
+link:https://jira.host.org/browse/A-1[Jira:A-1]
+
+[id="Jira-A-2"]
+.A private bug
+
+* One & two
+* Three
+
+Jira:A-2footnoteref:[PrivateTicketFootnote]
+"#
+            .into(),
+            included_modules: None,
+        };
+        let chapter = Module::WithContent {
+            file_name: "assembly_bug-fixes.adoc".into(),
+            text: r#"[id="bug-fixes"]
+= Bug fixes
+
+include::ref_bug-fixes-kernel.adoc[leveloffset=+1]
+"#
+            .into(),
+            included_modules: Some(vec![reference]),
+        };
+        let summary = r#"[appendix]
+= List of tickets by component
+
+[cols="1,2", options="header"]
+|===
+| Component | Tickets
+| kernel | xref:Jira-A-1[Jira:A-1]
+|===
+"#;
+
+        let book = book(&[chapter], summary, Some(PRIVATE_FOOTNOTE), "internal.xml");
+        let body = book
+            .strip_prefix(BOOK_START)
+            .expect("The book has no header.");
+
+        assert_eq!(
+            body,
+            r#"<chapter xml:id="bug-fixes">
+<title>Bug fixes</title>
+<section xml:id="bug-fixes-kernel">
+<title>Kernel</title>
+<section xml:id="Jira-A-1">
+<title>A fixed bug</title>
+<para>The <literal>--force</literal> option <emphasis role="strong">no longer</emphasis> fails.</para>
+<note>
+<para>Reboot.</para>
+</note>
+<para><link xlink:href="https://jira.host.org/browse/A-1">Jira:A-1</link></para>
+</section>
+<section xml:id="Jira-A-2">
+<title>A private bug</title>
+<itemizedlist>
+<listitem><para>One &amp; two</para></listitem>
+<listitem><para>Three</para></listitem>
+</itemizedlist>
+<para>Jira:A-2<footnote xml:id="footnote-PrivateTicketFootnote"><para>This ticket is not publicly accessible. Therefore, the release note does not include a link to the ticket.</para></footnote></para>
+</section>
+</section>
+</chapter>
+<appendix>
+<title>List of tickets by component</title>
+<informaltable>
+<tgroup cols="2">
+<thead>
+<row>
+<entry>Component</entry>
+<entry>Tickets</entry>
+</row>
+</thead>
+<tbody>
+<row>
+<entry>kernel</entry>
+<entry><link linkend="Jira-A-1">Jira:A-1</link></entry>
+</row>
+</tbody>
+</tgroup>
+</informaltable>
+</appendix>
+</book>
+"#
+        );
+    }
+
+    #[test]
+    fn keep_appendices_out_of_chapters() {
+        let chapter = Module::WithContent {
+            file_name: "ref_bug-fixes.adoc".into(),
+            text: "[id=\"bug-fixes\"]\n= Bug fixes\n\nFixed.\n".into(),
+            included_modules: None,
+        };
+        let unplaced = Module::WithContent {
+            file_name: "ref_unplaced-release-notes.adoc".into(),
+            text: "[appendix]\n[id=\"unplaced-release-notes\"]\n= Unplaced release notes\n\nForgotten.\n"
+                .into(),
+            included_modules: None,
+        };
+        let summary = "[appendix]\n= List of tickets by component\n";
+
+        let book = book(&[chapter, unplaced], summary, None, "internal.xml");
+
+        assert!(book.contains("<chapter xml:id=\"bug-fixes\">"));
+        assert!(book.contains("<appendix xml:id=\"unplaced-release-notes\">"));
+        assert!(!book.contains("<chapter xml:id=\"unplaced-release-notes\">"));
+    }
+
+    #[test]
+    fn separate_untitled_notes() {
+        let reference = Module::WithContent {
+            file_name: "ref_bug-fixes.adoc".into(),
+            text: r#"[id="bug-fixes"]
+= Bug fixes
+
+[id="Jira-A-1"]
+.A fixed bug
+
+Fixed.
+
+[id="Jira-A-2"]
+A bug without a title.
+
+[IMPORTANT]
+====
+Update first.
+====
+
+[id="Jira-A-3_bug-fixes"]
+.A repeated bug
+
+Repeated.
+"#
+            .into(),
+            included_modules: None,
+        };
+
+        let book = book(&[reference], "", None, "internal.xml");
+
+        assert!(book.contains(
+            r#"<title>A fixed bug</title>
+<para>Fixed.</para>
+</section>
+<section xml:id="Jira-A-2" role="untitled">
+<title/>
+<para>A bug without a title.</para>
+<important>
+<para>Update first.</para>
+</important>
+</section>
+<section xml:id="Jira-A-3_bug-fixes">
+<title>A repeated bug</title>"#
+        ));
+    }
+
+    /// Raw HTML and comments that aren't valid XML comments appear as text.
+    #[test]
+    fn escape_html_comments() {
+        let reference = Module::WithContent {
+            file_name: "ref_bug-fixes.adoc".into(),
+            text: r#"[id="bug-fixes"]
+= Bug fixes
+
+++++
+<!-- a raw -- comment -->
+<b>Bold</b>
+++++
+"#
+            .into(),
+            included_modules: None,
+        };
+
+        let book = book(&[reference], "", None, "internal.xml");
+
+        assert!(book.contains("<!-- acorns: unconverted AsciiDoc -->"));
+        assert!(book.contains("&lt;!-- a raw -- comment --&gt;"));
+        assert!(book.contains("&lt;b&gt;Bold&lt;/b&gt;"));
+        assert!(!book.contains("<b>"));
+    }
+}
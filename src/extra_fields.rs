@@ -16,7 +16,6 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-use std::convert::TryFrom;
 use std::fmt;
 use std::string::ToString;
 
@@ -30,36 +29,52 @@ use jira_query::Issue;
 use crate::config::tracker;
 
 /// The status or progress of the release note.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum DocTextStatus {
     Approved,
     InProgress,
     NoDocumentation,
+    /// The field holds a value that's neither built in nor configured for the tracker.
+    /// Keep the value so that the status table can report it.
+    Unrecognized(String),
 }
 
-impl TryFrom<&str> for DocTextStatus {
-    type Error = color_eyre::eyre::Error;
-
-    fn try_from(string: &str) -> Result<Self> {
-        // A case-insensitive comparison
-        match string.to_lowercase().as_str() {
-            "+" | "done" => Ok(Self::Approved),
-            "?" | "proposed" | "in progress" | "unset" => Ok(Self::InProgress),
-            // TODO: Does "Upstream only" really mean to skip this RN?
-            "-" | "rejected" | "upstream only" => Ok(Self::NoDocumentation),
-            _ => Err(eyre!("Unrecognized doc text status value: {:?}", string)),
+impl DocTextStatus {
+    /// Recognize the doc text status from the value of the field or flag.
+    ///
+    /// The values configured for the tracker in `trackers.yaml` take precedence
+    /// over the built-in values. The comparison is case-insensitive.
+    pub fn from_value(value: &str, custom: &tracker::DocTextStatusValues) -> Self {
+        let lowercase = value.to_lowercase();
+        let is_listed = |values: &[String]| values.iter().any(|v| v.to_lowercase() == lowercase);
+
+        if is_listed(&custom.approved) {
+            Self::Approved
+        } else if is_listed(&custom.in_progress) {
+            Self::InProgress
+        } else if is_listed(&custom.no_documentation) {
+            Self::NoDocumentation
+        } else {
+            match lowercase.as_str() {
+                "+" | "done" => Self::Approved,
+                "?" | "proposed" | "in progress" | "unset" => Self::InProgress,
+                // If "Upstream only" means something else in your tracker,
+                // map it to another status in `trackers.yaml`.
+                "-" | "rejected" | "upstream only" => Self::NoDocumentation,
+                _ => Self::Unrecognized(value.to_string()),
+            }
         }
     }
 }
 
 impl fmt::Display for DocTextStatus {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let display = match self {
-            Self::Approved => "Done",
-            Self::InProgress => "WIP",
-            Self::NoDocumentation => "No docs",
-        };
-        write!(f, "{display}")
+        match self {
+            Self::Approved => write!(f, "Done"),
+            Self::InProgress => write!(f, "WIP"),
+            Self::NoDocumentation => write!(f, "No docs"),
+            Self::Unrecognized(value) => write!(f, "Unknown: {value}"),
+        }
     }
 }
 
@@ -335,22 +350,25 @@ impl ExtraFields for Bug {
         let mut errors = Vec::new();
         // Record all empty but potentially okay fields.
         let mut empty_fields: Vec<&str> = Vec::new();
+        // Record the first value that's set but unrecognized.
+        let mut unrecognized = None;
 
         // If the RDT flag is unset, use this:
         let default_rdt = DocTextStatus::InProgress;
 
         for flag in fields {
             if let Some(rdt) = self.get_flag(flag) {
-                match DocTextStatus::try_from(rdt) {
-                    Ok(status) => {
-                        return status;
-                    }
-                    Err(error) => {
+                match DocTextStatus::from_value(rdt, config.doc_text_status_values()) {
+                    DocTextStatus::Unrecognized(value) => {
                         errors.push(eyre!(
-                            "Failed to extract the doc text status from flag {}.",
-                            flag
+                            "Unrecognized doc text status value in flag {}: {:?}",
+                            flag,
+                            value
                         ));
-                        errors.push(error);
+                        unrecognized.get_or_insert(DocTextStatus::Unrecognized(value));
+                    }
+                    status => {
+                        return status;
                     }
                 }
             } else {
@@ -370,6 +388,12 @@ impl ExtraFields for Bug {
                 empty_fields.join(", ")
             );
         }
+
+        // An unrecognized value is more useful in the status table than the default value.
+        if let Some(unrecognized) = unrecognized {
+            return unrecognized;
+        }
+
         // In case of both errors, return the default RDT value.
         default_rdt
     }
@@ -623,15 +647,20 @@ impl ExtraFields for Issue {
                         return default_status;
                     }
                     // If the field is set (Some value), use the regular string parsing.
-                    Some(string) => match DocTextStatus::try_from(string) {
-                        Ok(status) => {
-                            return status;
-                        }
-                        Err(e) => {
-                            errors.push(e);
-                            return default_status;
+                    Some(string) => {
+                        let status =
+                            DocTextStatus::from_value(string, config.doc_text_status_values());
+
+                        if let DocTextStatus::Unrecognized(value) = &status {
+                            log::warn!(
+                                "Unrecognized doc text status value in {}: {:?}",
+                                Id::Jira(&self.key),
+                                value
+                            );
                         }
-                    },
+
+                        return status;
+                    }
                 }
             };
         }
@@ -674,3 +703,35 @@ impl ExtraFields for Issue {
         format!("{}/browse/{}", tracker.host(), &self.key)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_doc_text_status_values() {
+        let custom = tracker::DocTextStatusValues {
+            approved: vec!["Ready for publication".to_string()],
+            in_progress: Vec::new(),
+            no_documentation: vec!["Not required".to_string(), "Upstream only".to_string()],
+        };
+
+        assert_eq!(
+            DocTextStatus::from_value("ready for Publication", &custom),
+            DocTextStatus::Approved
+        );
+        assert_eq!(
+            DocTextStatus::from_value("Not required", &custom),
+            DocTextStatus::NoDocumentation
+        );
+        // The built-in values still apply.
+        assert_eq!(
+            DocTextStatus::from_value("Proposed", &custom),
+            DocTextStatus::InProgress
+        );
+        assert_eq!(
+            DocTextStatus::from_value("Maybe later", &custom),
+            DocTextStatus::Unrecognized("Maybe later".to_string())
+        );
+    }
+}
@@ -20,7 +20,8 @@ along with this program.  If not, see <https://www.gnu.org/licenses/>.
 //! based on an optional footnote found in the manual AsciiDoc files in the docs repo.
 //!
 //! If any manual AsciiDoc file defines the `PrivateTicketFootnote` footnote, private tickets
-//! will add the footnote to the non-clickable ticket signature.
+//! will add the footnote to the non-clickable ticket signature. Output formats other than
+//! AsciiDoc repeat the text of the footnote definition.
 
 use std::fs;
 use std::path::Path;
@@ -32,30 +33,41 @@ use regex::Regex;
 
 use crate::REGEX_ERROR;
 
-/// This regex looks for a footnote definition with the `PrivateTicketFootnote` ID.
+/// This regex looks for a footnote definition with the `PrivateTicketFootnote` ID,
+/// and captures the text of the footnote.
 static FOOTNOTE_ATTR_REGEX: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"footnoteref:\[PrivateTicketFootnote,.+\]").expect(REGEX_ERROR));
+    Lazy::new(|| Regex::new(r"footnoteref:\[PrivateTicketFootnote,([^\]]+)\]").expect(REGEX_ERROR));
+
+/// An AsciiDoc attribute definition, such as `:private-footnote-text: This ticket is private.`
+static ATTR_DEFINITION_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^:([\w-]+):\s+(.+)$").expect(REGEX_ERROR));
+
+/// An AsciiDoc attribute reference, such as `{private-footnote-text}`.
+static ATTR_REFERENCE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{([\w-]+)\}").expect(REGEX_ERROR));
 
 /// Search the AsciiDoc files in the RN project and see if any of them defines
-/// the `PrivateTicketFootnote` footnote. Return `true` if the footnote is defined.
-pub fn is_footnote_defined(project: &Path) -> Result<bool> {
+/// the `PrivateTicketFootnote` footnote. Return the text of the footnote if it's defined.
+pub fn defined_footnote(project: &Path) -> Result<Option<String>> {
     for result in Walk::new(project) {
         // Each item yielded by the iterator is either a directory entry or an error.
         let dir_entry = result?;
 
         let file_path = dir_entry.path();
 
-        if is_file_adoc(file_path) && file_contains_footnote(file_path)? {
-            log::info!("The private ticket footnote is defined.");
-            return Ok(true);
+        if is_file_adoc(file_path) {
+            if let Some(text) = footnote_in_file(file_path)? {
+                log::info!("The private ticket footnote is defined.");
+                return Ok(Some(text));
+            }
         }
     }
 
-    Ok(false)
+    Ok(None)
 }
 
 /// Estimate if the given file is an AsciiDoc file.
-fn is_file_adoc(path: &Path) -> bool {
+pub fn is_file_adoc(path: &Path) -> bool {
     let adoc_extensions = ["adoc", "asciidoc"];
 
     let file_ext = path.extension().and_then(|ext| ext.to_str());
@@ -69,18 +81,59 @@ fn is_file_adoc(path: &Path) -> bool {
     false
 }
 
-/// Return `true` if the given file contains the footnote defined
-/// in the `FOOTNOTE_ATTR_REGEX` regular expression.
-fn file_contains_footnote(path: &Path) -> Result<bool> {
+/// Return the text of the footnote defined in the `FOOTNOTE_ATTR_REGEX` regular expression,
+/// if the given file contains it.
+fn footnote_in_file(path: &Path) -> Result<Option<String>> {
     let text = fs::read_to_string(path)
         .wrap_err("Cannot read AsciiDoc file in the project repository.")?;
 
-    let found_attr = text.lines().any(|line| {
-        // Detect and reject basic line comments.
-        !line.starts_with("//") &&
-        // If any line contains the footnote attribute definition, return `true`.
-        FOOTNOTE_ATTR_REGEX.is_match(line)
+    Ok(footnote_in_text(&text))
+}
+
+/// Find the footnote definition in the AsciiDoc text. Replace the attributes
+/// in the footnote text with their values, if the same text defines them.
+fn footnote_in_text(text: &str) -> Option<String> {
+    // Detect and reject basic line comments.
+    let lines = || text.lines().filter(|line| !line.starts_with("//"));
+
+    let footnote = lines().find_map(|line| FOOTNOTE_ATTR_REGEX.captures(line))?;
+
+    let resolved = ATTR_REFERENCE_REGEX.replace_all(&footnote[1], |caps: &regex::Captures| {
+        lines()
+            .filter_map(|line| ATTR_DEFINITION_REGEX.captures(line))
+            .find(|definition| definition[1] == caps[1])
+            .map_or_else(
+                || caps[0].to_string(),
+                |definition| definition[2].to_string(),
+            )
     });
 
-    Ok(found_attr)
+    Some(resolved.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_footnote_text() {
+        let text = "\
+:private-footnote-text: This ticket is private.
+
+// footnoteref:[PrivateTicketFootnote,Commented out.]
+Private tickets feature a footnote{blank}footnoteref:[PrivateTicketFootnote,{private-footnote-text}].
+";
+        assert_eq!(
+            footnote_in_text(text).as_deref(),
+            Some("This ticket is private.")
+        );
+
+        // An attribute that the file doesn't define stays as it is.
+        assert_eq!(
+            footnote_in_text("footnoteref:[PrivateTicketFootnote,See {policy}.]").as_deref(),
+            Some("See {policy}.")
+        );
+
+        assert_eq!(footnote_in_text("footnoteref:[OtherFootnote,Text.]"), None);
+    }
 }
@@ -0,0 +1,223 @@
+/*
+acorns: Generate an AsciiDoc release notes document from tracking tickets.
+Copyright (C) 2023  Marek Suchánek  <msuchane@redhat.com>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! A standalone HTML preview of a document variant.
+//!
+//! The preview is a single page that needs no AsciiDoc toolchain to build.
+//! It converts the generated modules to Markdown first, and renders the Markdown to HTML.
+//! Therefore, it supports the same subset of AsciiDoc as the Markdown output.
+
+use askama::Template;
+use color_eyre::eyre::Result;
+use once_cell::sync::Lazy;
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::custom_templates::{self, CustomTemplates};
+use crate::markdown;
+use crate::templating::Module;
+use crate::REGEX_ERROR;
+
+/// The anchor that the Markdown conversion places before a heading.
+/// It's the only raw HTML that the preview keeps.
+static ANCHOR_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^<a id="([^"<>]+)">$"#).expect(REGEX_ERROR));
+
+/// A link in the navigation of the preview, which points to a heading.
+#[derive(Debug, PartialEq, Serialize)]
+struct NavigationEntry {
+    /// The heading level, starting at 1.
+    level: usize,
+    id: String,
+    title: String,
+}
+
+/// All the data that the preview page needs to render.
+#[derive(Template, Serialize)]
+#[template(path = "preview.html")]
+struct PreviewTemplate<'a> {
+    variant: &'a str,
+    navigation: &'a [NavigationEntry],
+    body: &'a str,
+}
+
+/// Render the modules and the summary appendix of a document variant as a single HTML page.
+pub fn render(
+    modules: &[Module],
+    summary: &str,
+    private_footnote: Option<&str>,
+    variant: &str,
+    custom_templates: &CustomTemplates,
+) -> Result<String> {
+    let markdown = markdown::single_page(
+        modules,
+        summary,
+        private_footnote,
+        &format!("preview-{variant}.html"),
+    );
+    let (body, navigation) = to_html(&markdown);
+
+    let template = PreviewTemplate {
+        variant,
+        navigation: &navigation,
+        body: &body,
+    };
+
+    custom_templates.render(custom_templates::PREVIEW, &template)
+}
+
+/// Render Markdown to HTML, and list the headings that have anchors
+/// in the first three levels.
+fn to_html(markdown: &str) -> (String, Vec<NavigationEntry>) {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES;
+    let events = escape_html(Parser::new_ext(markdown, options).collect());
+
+    let mut navigation = Vec::new();
+    let mut last_anchor: Option<String> = None;
+    let mut heading: Option<(HeadingLevel, String)> = None;
+
+    for event in &events {
+        match event {
+            Event::Html(html) | Event::InlineHtml(html) => {
+                if let Some(caps) = ANCHOR_REGEX.captures(html) {
+                    last_anchor = Some(caps[1].to_string());
+                }
+            }
+            Event::Start(Tag::Heading { level, .. }) => {
+                heading = Some((*level, String::new()));
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, title)) = &mut heading {
+                    title.push_str(text);
+                } else {
+                    // An anchor only belongs to a heading that directly follows it.
+                    last_anchor = None;
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let (Some((level, title)), Some(id)) = (heading.take(), last_anchor.take()) {
+                    if level <= HeadingLevel::H3 {
+                        navigation.push(NavigationEntry {
+                            level: level as usize,
+                            id,
+                            title,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut body = String::new();
+    pulldown_cmark::html::push_html(&mut body, events.into_iter());
+
+    (body, navigation)
+}
+
+/// Keep the anchors that the Markdown conversion generates as raw HTML. Display any other
+/// raw HTML as text, because it comes from the release notes and the page must not run it.
+fn escape_html(events: Vec<Event>) -> Vec<Event> {
+    let mut escaped = Vec::with_capacity(events.len());
+    let mut events = events.into_iter().peekable();
+
+    while let Some(event) = events.next() {
+        // An empty anchor is an opening and a closing tag in a row.
+        let opens_anchor = matches!(&event, Event::InlineHtml(html) if ANCHOR_REGEX.is_match(html));
+        let closes_anchor =
+            matches!(events.peek(), Some(Event::InlineHtml(html)) if html.as_ref() == "</a>");
+
+        match event {
+            Event::InlineHtml(_) if opens_anchor && closes_anchor => {
+                escaped.push(event);
+                escaped.extend(events.next());
+            }
+            Event::Html(html) | Event::InlineHtml(html) => escaped.push(Event::Text(html)),
+            event => escaped.push(event),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn navigate_headings() {
+        let markdown = r#"<a id="bug-fixes"></a>
+
+# Bug fixes
+
+<a id="bug-fixes-kernel"></a>
+
+## The `kernel` component
+
+<a id="Jira-A-1"></a>
+
+**A fixed bug**
+
+See [the fixed bug](#Jira-A-1).
+
+### No anchor
+"#;
+        let (body, navigation) = to_html(markdown);
+
+        assert_eq!(
+            navigation,
+            [
+                NavigationEntry {
+                    level: 1,
+                    id: "bug-fixes".into(),
+                    title: "Bug fixes".into(),
+                },
+                NavigationEntry {
+                    level: 2,
+                    id: "bug-fixes-kernel".into(),
+                    title: "The kernel component".into(),
+                },
+            ]
+        );
+        assert!(body.contains(r##"<a href="#Jira-A-1">the fixed bug</a>"##));
+        assert!(body.contains("<h2>The <code>kernel</code> component</h2>"));
+    }
+
+    #[test]
+    fn escape_raw_html() {
+        let markdown = r#"<a id="Jira-A-1"></a>
+
+**A fixed bug**
+
+<script>alert("block")</script>
+
+The fix <img src="x" onerror="alert('inline')"> works.
+
+<a id="Jira-A-2" onmouseover="alert('anchor')"></a>
+"#;
+        let (body, _) = to_html(markdown);
+
+        assert!(body.contains(r#"<a id="Jira-A-1"></a>"#));
+        assert!(!body.contains("<script>"));
+        assert!(body.contains("&lt;script&gt;"));
+        assert!(!body.contains("<img"));
+        assert!(body.contains("&lt;img src="));
+        assert!(!body.contains("<a id=\"Jira-A-2\""));
+    }
+}
@@ -36,10 +36,16 @@ use color_eyre::eyre::{Result, WrapErr};
 pub mod cli;
 mod config;
 mod convert;
+mod custom_templates;
+mod docbook;
 mod extra_fields;
 mod footnote;
+mod html_preview;
 mod init;
 mod logging;
+mod markdown;
+mod markup;
+mod migrate;
 mod note;
 mod references;
 mod status_report;
@@ -47,14 +53,17 @@ mod summary_list;
 mod templating;
 mod ticket_abstraction;
 mod tracker_access;
+mod translation;
 
 use cli::{Cli, Commands};
 
 // use config::tracker::Service;
-use templating::{DocumentVariant, Module};
+use templating::{DocumentVariant, Module, Outline};
 
-use crate::config::Project;
+use crate::config::{OutputFormat, Project};
+use crate::custom_templates::CustomTemplates;
 pub use crate::ticket_abstraction::AbstractTicket;
+use crate::translation::{Message, Translations};
 
 /// A shared error message that displays if the static regular expressions
 /// are invalid, and the regex library can't parse them.
@@ -67,8 +76,8 @@ pub fn run(cli: &Cli) -> Result<()> {
 
     match &cli.command {
         // If the user picked the `build` subcommand, build the specified release notes project directory
-        Commands::Build { project } => {
-            build_rn_project(project)?;
+        Commands::Build { project, lang } => {
+            build_rn_project(project, lang.as_deref())?;
         }
         // If the user picked the `ticket` subcommand, fetch and display a single ticket
         Commands::Ticket { .. } => {
@@ -83,6 +92,8 @@ pub fn run(cli: &Cli) -> Result<()> {
         }
         Commands::Init { directory } => init::initialize_directory(directory)
             .wrap_err("Failed to initialize the project directory.")?,
+        Commands::Migrate { apply, project } => migrate::migrate(project, *apply)
+            .wrap_err("Failed to migrate the project directory.")?,
     }
 
     Ok(())
@@ -117,69 +128,153 @@ fn display_single_ticket() -> Result<()> {
 
 /// Run the `build` subcommand, which build the release notes project that's configured
 /// in the project directory specified on the command line, or in the working directory.
-fn build_rn_project(project_dir: &Path) -> Result<()> {
+///
+/// With a language, the build uses the translated texts where available, and saves
+/// the files in a separate output directory.
+fn build_rn_project(project_dir: &Path, lang: Option<&str>) -> Result<()> {
     // TODO: Recognize the optional paths to different config files.
     let project = Project::new(project_dir)?;
 
+    let translations = lang
+        .map(|lang| Translations::load(&project.translations_dir, lang))
+        .transpose()?;
+
     log::info!("Building the release notes project.");
 
-    let document = Document::new(&project)?;
+    let document = Document::new(&project, translations.as_ref())?;
+
+    let generated_dir = match lang {
+        Some(lang) => project.localized_dir(lang),
+        None => project.generated_dir.clone(),
+    };
 
-    document.write_variants(&project.generated_dir)?;
+    document.write_variants(
+        &generated_dir,
+        &project.settings.formats,
+        &project.custom_templates,
+        project.private_footnote.as_deref(),
+    )?;
 
     Ok(())
 }
 
 /// Holds all the data generated from the project configuration before writing them to disk.
 struct Document {
-    internal_modules: Vec<Module>,
-    external_modules: Vec<Module>,
+    /// The internal and the external variant, followed by the variants that the project defines.
+    variants: Vec<GeneratedVariant>,
     status_table: String,
     json_status: String,
-    internal_summary: String,
-    external_summary: String,
+    messages: Vec<Message>,
+}
+
+/// The formatted content of a single document variant.
+struct GeneratedVariant {
+    /// The name of the variant, which names its files in the additional output formats.
+    name: String,
+    /// The output subdirectory of the AsciiDoc modules.
+    directory: String,
+    modules: Vec<Module>,
+    outline: Vec<Outline>,
+    summary: String,
 }
 
 impl Document {
-    /// Prepare all populated and formatted modules that result from the RN project configuration.
-    /// Returns a tuple with the document generated in two variants: (Internal, External).
-    fn new(project: &Project) -> Result<Self> {
-        let abstract_tickets =
+    /// Prepare all populated and formatted modules that result from the RN project configuration,
+    /// in every document variant.
+    ///
+    /// With translations, the document uses the translated texts, but the status checks
+    /// still evaluate the original tickets.
+    fn new(project: &Project, translations: Option<&Translations>) -> Result<Self> {
+        let mut abstract_tickets =
             ticket_abstraction::from_queries(&project.tickets, &project.trackers)?;
 
-        // Filter internal and external tickets here before formatting the document.
-        // That way, functions in `templating` don't have to keep checking if they're
-        // working on the right ticket subset.
-        let tickets_for_internal = variant_tickets(&abstract_tickets, DocumentVariant::Internal);
-        let tickets_for_external = variant_tickets(&abstract_tickets, DocumentVariant::External);
-
-        let internal_modules = templating::format_document(
-            &tickets_for_internal,
-            &project.templates,
-            DocumentVariant::Internal,
-            project.private_footnote,
-        );
-        let external_modules = templating::format_document(
-            &tickets_for_external,
-            &project.templates,
-            DocumentVariant::External,
-            project.private_footnote,
-        );
-
-        let (status_table, json_status) = status_report::analyze_status(&abstract_tickets)?;
-
-        let internal_summary =
-            summary_list::appendix(&tickets_for_internal, DocumentVariant::Internal)?;
-        let external_summary =
-            summary_list::appendix(&tickets_for_external, DocumentVariant::External)?;
+        // Generate the missing titles before both the formatting and the status checks.
+        let titles = &project.settings.titles;
+        if titles.from_summary {
+            for ticket in &mut abstract_tickets {
+                ticket.add_title_from_summary(titles.cleanup.as_ref());
+            }
+        }
+
+        // The translatable texts always come from the English configuration and tickets.
+        let messages = translation::messages(&project.templates, &abstract_tickets);
+
+        let localized = translations.map(|translations| {
+            translations.report_coverage(&messages);
+            (
+                templating::localize(&project.templates, translations),
+                translation::localize_tickets(&abstract_tickets, translations),
+            )
+        });
+        let (templates, tickets) = match &localized {
+            Some((templates, tickets)) => (templates, tickets.as_slice()),
+            None => (&project.templates, abstract_tickets.as_slice()),
+        };
+
+        // The internal variant goes first, so that it reports the ticket usage statistics.
+        let builtin_variants = [
+            (
+                config::Variant::builtin("internal", true),
+                DocumentVariant::Internal,
+            ),
+            (
+                config::Variant::builtin("external", false),
+                DocumentVariant::External,
+            ),
+        ];
+        let custom_variants = project.settings.variants.iter().map(|variant| {
+            let document_variant = DocumentVariant::Custom {
+                debug_info: variant.debug_info,
+            };
+            (variant.clone(), document_variant)
+        });
+
+        let variants = builtin_variants
+            .into_iter()
+            .chain(custom_variants)
+            .map(|(variant, document_variant)| {
+                // Filter the tickets of the variant here before formatting the document.
+                // That way, functions in `templating` don't have to keep checking if they're
+                // working on the right ticket subset.
+                let variant_tickets: Vec<&AbstractTicket> =
+                    tickets.iter().filter(|t| variant.includes(t)).collect();
+
+                let (modules, outline) = templating::format_document(
+                    &variant_tickets,
+                    templates,
+                    document_variant,
+                    project.private_footnote.is_some(),
+                    &project.custom_templates,
+                )?;
+
+                let summary = summary_list::appendix(
+                    &variant_tickets,
+                    document_variant,
+                    &project.settings.appendix,
+                    &project.custom_templates,
+                )?;
+
+                Ok(GeneratedVariant {
+                    directory: variant.directory().to_string(),
+                    name: variant.name,
+                    modules,
+                    outline,
+                    summary,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let (status_table, json_status) = status_report::analyze_status(
+            &abstract_tickets,
+            &project.settings.status,
+            &project.custom_templates,
+        )?;
 
         Ok(Self {
-            internal_modules,
-            external_modules,
+            variants,
             status_table,
             json_status,
-            internal_summary,
-            external_summary,
+            messages,
         })
     }
 
@@ -251,8 +346,14 @@ impl Document {
         Ok(())
     }
 
-    /// Write the formatted RN modules of both document variants as files to the output directory.
-    fn write_variants(&self, generated_dir: &Path) -> Result<()> {
+    /// Write the formatted RN modules of all document variants as files to the output directory.
+    fn write_variants(
+        &self,
+        generated_dir: &Path,
+        formats: &[OutputFormat],
+        custom_templates: &CustomTemplates,
+        private_footnote: Option<&str>,
+    ) -> Result<()> {
         log::info!("Saving the generated release notes.");
 
         // Remove all previously generated content so that it doesn't interfere with the new build.
@@ -260,20 +361,78 @@ impl Document {
             fs::remove_dir_all(generated_dir)?;
         }
 
-        let internal_dir = generated_dir.join("internal");
-        let external_dir = generated_dir.join("external");
-
         // Save the newly generated files.
-        Self::write_variant(
-            &self.internal_modules,
-            &self.internal_summary,
-            &internal_dir,
-        )?;
-        Self::write_variant(
-            &self.external_modules,
-            &self.external_summary,
-            &external_dir,
-        )?;
+        for variant in &self.variants {
+            Self::write_variant(
+                &variant.modules,
+                &variant.summary,
+                &generated_dir.join(&variant.directory),
+            )?;
+        }
+
+        // Save the additional output formats.
+        for format in formats {
+            match format {
+                OutputFormat::Markdown => {
+                    let markdown_dir = generated_dir.join("markdown");
+                    for variant in &self.variants {
+                        markdown::write_variant(
+                            &variant.modules,
+                            &variant.summary,
+                            private_footnote,
+                            &markdown_dir.join(&variant.directory),
+                        )?;
+                    }
+                }
+                OutputFormat::Html => {
+                    for variant in &self.variants {
+                        let preview = html_preview::render(
+                            &variant.modules,
+                            &variant.summary,
+                            private_footnote,
+                            &variant.name,
+                            custom_templates,
+                        )?;
+                        let preview_file =
+                            generated_dir.join(format!("preview-{}.html", variant.name));
+                        log::debug!("Writing file: {}", preview_file.display());
+                        fs::write(preview_file, preview)
+                            .wrap_err("Failed to write the HTML preview.")?;
+                    }
+                }
+                OutputFormat::Json => {
+                    let json_dir = generated_dir.join("json");
+                    fs::create_dir_all(&json_dir)?;
+                    for variant in &self.variants {
+                        let outline = serde_json::json!({
+                            "variant": variant.name,
+                            "chapters": variant.outline,
+                        });
+                        let json_file = json_dir.join(format!("{}.json", variant.name));
+                        log::debug!("Writing file: {}", json_file.display());
+                        fs::write(json_file, serde_json::to_string_pretty(&outline)?)
+                            .wrap_err("Failed to write the JSON export.")?;
+                    }
+                }
+                OutputFormat::Po => {
+                    let pot_file = generated_dir.join("translations.pot");
+                    log::debug!("Writing file: {}", pot_file.display());
+                    fs::write(pot_file, translation::po_template(&self.messages))
+                        .wrap_err("Failed to write the PO template.")?;
+                }
+                OutputFormat::Docbook => {
+                    let docbook_dir = generated_dir.join("docbook");
+                    for variant in &self.variants {
+                        docbook::write_variant(
+                            &variant.modules,
+                            &variant.summary,
+                            private_footnote,
+                            &docbook_dir.join(format!("{}.xml", variant.name)),
+                        )?;
+                    }
+                }
+            }
+        }
 
         // Save the status table.
         let html_status_file = generated_dir.join("status-table.html");
@@ -290,19 +449,3 @@ impl Document {
         Ok(())
     }
 }
-
-/// Select only those tickets that belong in the Internal or External variant.
-fn variant_tickets(
-    all_tickets: &[AbstractTicket],
-    variant: DocumentVariant,
-) -> Vec<&AbstractTicket> {
-    match variant {
-        // The internal variant accepts all tickets.
-        DocumentVariant::Internal => all_tickets.iter().collect(),
-        // The external variant accepts only finished and approved tickets.
-        DocumentVariant::External => all_tickets
-            .iter()
-            .filter(|t| t.doc_text_status == extra_fields::DocTextStatus::Approved)
-            .collect(),
-    }
-}
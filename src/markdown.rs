@@ -0,0 +1,844 @@
+/*
+acorns: Generate an AsciiDoc release notes document from tracking tickets.
+Copyright (C) 2023  Marek Suchánek  <msuchane@redhat.com>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! A renderer that converts the generated AsciiDoc modules into CommonMark.
+//!
+//! Each top-level chapter becomes one Markdown file, which contains the chapter
+//! and all the modules that it includes, with their headings shifted by the level offset.
+//! Cross-references between release notes point to the Markdown file that sets the anchor.
+//!
+//! The conversion covers the common AsciiDoc constructs in release notes. If a line contains
+//! AsciiDoc that the conversion can't handle, the line stays as it is, an HTML comment
+//! marks it in the output, and acorns logs a warning.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use color_eyre::eyre::{Result, WrapErr};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::templating::Module;
+use crate::REGEX_ERROR;
+
+/// The file name of the appendix that lists tickets by component.
+const SUMMARY_FILE: &str = "ref_list-of-tickets-by-component.md";
+
+/// The HTML comment that marks a line of AsciiDoc that the conversion didn't handle.
+const UNCONVERTED_MARK: &str = "<!-- acorns: unconverted AsciiDoc -->";
+
+/// This regex matches the three forms of an AsciiDoc block anchor:
+/// `[id="anchor"]`, `[[anchor]]`, and `[#anchor]`.
+static ANCHOR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"^(?:\[id=["']?([^"'\]]+)["']?\]|\[\[([^\],]+)(?:,[^\]]*)?\]\]|\[#([^\].%,]+)[^\]]*\])$"#,
+    )
+    .expect(REGEX_ERROR)
+});
+/// A section heading, such as `== Title`.
+static HEADING_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(={1,6}) (.+)$").expect(REGEX_ERROR));
+/// A block title, such as `.Title` or `..NET support`.
+/// It isn't an ordered list item or a literal block delimiter.
+static BLOCK_TITLE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\.(\.?[^.\s].*)$").expect(REGEX_ERROR));
+/// An unordered list item, such as `* Item` or `** Nested item`.
+static UNORDERED_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\*{1,5}|-) (.+)$").expect(REGEX_ERROR));
+/// An ordered list item, such as `. Item` or `.. Nested item`.
+static ORDERED_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\.{1,5}) (.+)$").expect(REGEX_ERROR));
+/// A description list item, such as `Term:: Description`.
+static DESCRIPTION_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\S.*?)(?:::|;;)(?:\s+(.*))?$").expect(REGEX_ERROR));
+/// An admonition paragraph, such as `NOTE: Text`.
+static ADMONITION_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(NOTE|TIP|IMPORTANT|WARNING|CAUTION): (.*)$").expect(REGEX_ERROR));
+/// A block attribute line, such as `[source,bash]`.
+static BLOCK_ATTRIBUTES_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\[(.*)\]$").expect(REGEX_ERROR));
+/// An attribute entry, such as `:product: Example`.
+static ATTRIBUTE_ENTRY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^:!?[\w-]+!?:(?:\s.*)?$").expect(REGEX_ERROR));
+/// A conditional preprocessor directive, such as `ifdef::attribute[]`.
+static CONDITIONAL_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:ifdef|ifndef|ifeval|endif)::.*\[.*\]$").expect(REGEX_ERROR));
+/// An include directive, with the level offset of the included file.
+static INCLUDE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^include::(.+?)\[(.*)\]$").expect(REGEX_ERROR));
+/// The level offset option of an include directive.
+static LEVELOFFSET_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"leveloffset=\+(\d)").expect(REGEX_ERROR));
+/// Any other block macro, such as `image::file.png[]`.
+static BLOCK_MACRO_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[a-z]+::\S*\[.*\]$").expect(REGEX_ERROR));
+
+/// The `link:` macro.
+static LINK_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"link:([^\s\[]+)\[([^\]]*)\]").expect(REGEX_ERROR));
+/// A URL with link text in brackets.
+static URL_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b((?:https?|ftp)://[^\s\[]+)\[([^\]]*)\]").expect(REGEX_ERROR));
+/// The `xref:` macro.
+static XREF_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"xref:([^\s\[]+)\[([^\]]*)\]").expect(REGEX_ERROR));
+/// The `<<anchor,text>>` cross-reference.
+static ANGLE_XREF_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<<([^,>]+)(?:,\s*([^>]+))?>>").expect(REGEX_ERROR));
+/// The deprecated `footnoteref:` macro.
+static FOOTNOTEREF_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"footnoteref:\[([^,\]]+)(?:,([^\]]*))?\]").expect(REGEX_ERROR));
+/// The `footnote:` macro, with an optional ID.
+static FOOTNOTE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"footnote:([\w-]*)\[([^\]]*)\]").expect(REGEX_ERROR));
+/// Constrained bold text, such as `*bold*`.
+static BOLD_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(^|[^\w*])\*([^*\s](?:[^*]*[^*\s])?)\*([^\w*]|$)").expect(REGEX_ERROR)
+});
+/// Unconstrained italic text, such as `__italic__`.
+static DOUBLE_ITALIC_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"__([^_]+)__").expect(REGEX_ERROR));
+/// Constrained italic text, such as `_italic_`.
+static ITALIC_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(^|[^\w_])_([^_\s](?:[^_]*[^_\s])?)_([^\w_]|$)").expect(REGEX_ERROR));
+/// A literal monospace passthrough, such as `` `+text+` ``.
+static LITERAL_MONOSPACE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"`\+\+?([^`]*?)\+\+?`").expect(REGEX_ERROR));
+/// A literal passthrough, such as `+text+`.
+static PASSTHROUGH_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(^|\s)\+([^+\s](?:[^+]*[^+\s])?)\+(\s|$)").expect(REGEX_ERROR));
+/// An attribute reference, such as `{product}`.
+static ATTRIBUTE_REFERENCE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{[\w-]+\}").expect(REGEX_ERROR));
+/// Other inline macros, which Markdown has no equivalent for.
+static INLINE_MACRO_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b(?:pass|kbd|btn|menu|image|icon|indexterm2?|anchor):[^\s\[]*\[[^\]]*\]")
+        .expect(REGEX_ERROR)
+});
+
+/// Convert the modules and the summary appendix of a document variant to Markdown,
+/// and write them as files to the output directory.
+///
+/// The `private_footnote` is the text of the footnote that explains why private tickets
+/// have no link, if the project defines it.
+pub fn write_variant(
+    modules: &[Module],
+    summary: &str,
+    private_footnote: Option<&str>,
+    out_dir: &Path,
+) -> Result<()> {
+    fs::create_dir_all(out_dir)?;
+
+    // Record which file sets each anchor first, so that cross-references
+    // can point across the Markdown files.
+    let mut anchors = HashMap::new();
+    for chapter in modules {
+        collect_anchors(chapter, &markdown_name(chapter.file_name()), &mut anchors);
+    }
+
+    for chapter in modules {
+        let file_name = markdown_name(chapter.file_name());
+        let mut converter = Converter::new(&file_name, &anchors, private_footnote);
+        converter.module(chapter, 0);
+
+        write_file(&out_dir.join(&file_name), &converter.finish())?;
+    }
+
+    let mut converter = Converter::new(SUMMARY_FILE, &anchors, private_footnote);
+    converter.text(summary, 0, &[]);
+    write_file(&out_dir.join(SUMMARY_FILE), &converter.finish())?;
+
+    Ok(())
+}
+
+/// Convert the modules and the summary appendix of a document variant to a single
+/// Markdown document, in which all cross-references point within the document.
+/// The `file_name` only identifies the document in warnings.
+pub fn single_page(
+    modules: &[Module],
+    summary: &str,
+    private_footnote: Option<&str>,
+    file_name: &str,
+) -> String {
+    let (chapters, appendix) = single_page_parts(modules, summary, private_footnote, file_name);
+    format!("{chapters}\n{appendix}")
+}
+
+/// Convert a document variant like `single_page`, but keep the chapters
+/// and the appendices as separate Markdown texts. The appendices are the summary,
+/// followed by the modules that are appendices, such as the unplaced release notes.
+pub fn single_page_parts(
+    modules: &[Module],
+    summary: &str,
+    private_footnote: Option<&str>,
+    file_name: &str,
+) -> (String, String) {
+    let mut anchors = HashMap::new();
+    for chapter in modules {
+        collect_anchors(chapter, file_name, &mut anchors);
+    }
+
+    let (appendices, chapters): (Vec<&Module>, Vec<&Module>) =
+        modules.iter().partition(|module| module.is_appendix());
+
+    let mut converter = Converter::new(file_name, &anchors, private_footnote);
+    for chapter in chapters {
+        converter.module(chapter, 0);
+        converter.output.push(String::new());
+    }
+    let chapters = converter.finish();
+
+    let mut converter = Converter::new(file_name, &anchors, private_footnote);
+    converter.text(summary, 0, &[]);
+    for appendix in appendices {
+        converter.output.push(String::new());
+        converter.module(appendix, 0);
+    }
+    let appendix = converter.finish();
+
+    (chapters, appendix)
+}
+
+fn write_file(path: &Path, text: &str) -> Result<()> {
+    log::debug!("Writing file: {}", path.display());
+    fs::write(path, text).wrap_err("Failed to write a generated Markdown file.")
+}
+
+/// Replace the `.adoc` extension of a generated file with `.md`.
+fn markdown_name(file_name: &str) -> String {
+    let stem = file_name.strip_suffix(".adoc").unwrap_or(file_name);
+    format!("{stem}.md")
+}
+
+/// Find the ID that an anchor line sets.
+fn anchor(line: &str) -> Option<&str> {
+    ANCHOR_REGEX.captures(line).and_then(|caps| {
+        caps.get(1)
+            .or_else(|| caps.get(2))
+            .or_else(|| caps.get(3))
+            .map(|id| id.as_str())
+    })
+}
+
+/// Record the anchors in the module and in the modules that it includes, recursively.
+fn collect_anchors(module: &Module, file_name: &str, anchors: &mut HashMap<String, String>) {
+    if let Module::WithContent {
+        text,
+        included_modules,
+        ..
+    } = module
+    {
+        for id in text.lines().filter_map(anchor) {
+            anchors
+                .entry(id.to_string())
+                .or_insert_with(|| file_name.to_string());
+        }
+        for included in included_modules.iter().flatten() {
+            collect_anchors(included, file_name, anchors);
+        }
+    }
+}
+
+/// The conversion of a single output Markdown file.
+struct Converter<'a> {
+    /// The name of the Markdown file that this converter produces.
+    file_name: &'a str,
+    /// The Markdown file that sets each anchor in the document.
+    anchors: &'a HashMap<String, String>,
+    /// The text of the private ticket footnote, if the project defines it.
+    private_footnote: Option<&'a str>,
+    output: Vec<String>,
+    /// The footnotes in this file, by ID, with their text, if known.
+    footnotes: Vec<(String, Option<String>)>,
+    /// The lines that the converter couldn't convert.
+    unconverted: Vec<String>,
+}
+
+impl<'a> Converter<'a> {
+    fn new(
+        file_name: &'a str,
+        anchors: &'a HashMap<String, String>,
+        private_footnote: Option<&'a str>,
+    ) -> Self {
+        Self {
+            file_name,
+            anchors,
+            private_footnote,
+            output: Vec::new(),
+            footnotes: Vec::new(),
+            unconverted: Vec::new(),
+        }
+    }
+
+    /// Convert a module. The included modules appear in place of their include directives.
+    fn module(&mut self, module: &Module, offset: usize) {
+        if let Module::WithContent {
+            text,
+            included_modules,
+            ..
+        } = module
+        {
+            self.text(
+                text,
+                offset,
+                included_modules.as_deref().unwrap_or_default(),
+            );
+        }
+    }
+
+    /// Record a line that can't be converted, and keep it in the output as it is.
+    fn flag(&mut self, line: &str) {
+        self.unconverted.push(line.to_string());
+        self.output.push(UNCONVERTED_MARK.to_string());
+        self.output.push(line.to_string());
+    }
+
+    /// Convert AsciiDoc text, line by line. The `offset` shifts the level of headings.
+    #[allow(clippy::too_many_lines)]
+    fn text(&mut self, text: &str, offset: usize, included: &[Module]) {
+        let mut lines = text.lines();
+        // The block attributes that apply to the next block.
+        let mut attributes: Option<String> = None;
+
+        while let Some(line) = lines.next() {
+            let block_attributes = attributes.take();
+
+            // Delimited blocks
+            match line {
+                // A comment block.
+                "////" => {
+                    lines.by_ref().take_while(|l| *l != "////").for_each(drop);
+                    continue;
+                }
+                // A listing or literal block.
+                "----" | "...." => {
+                    let language = block_attributes
+                        .as_deref()
+                        .and_then(|attrs| attrs.strip_prefix("source,"))
+                        .map_or("", |lang| lang.split(',').next().unwrap_or_default());
+                    self.output.push(format!("```{language}"));
+                    for code in lines.by_ref().take_while(|l| *l != line) {
+                        self.output.push(code.to_string());
+                    }
+                    self.output.push("```".to_string());
+                    continue;
+                }
+                // An example, sidebar, or quote block. An admonition block is an example block.
+                "====" | "****" | "____" => {
+                    let inner: Vec<&str> = lines.by_ref().take_while(|l| *l != line).collect();
+                    self.quote(
+                        &inner.join("\n"),
+                        block_attributes.as_deref(),
+                        offset,
+                        included,
+                    );
+                    continue;
+                }
+                // An open block only groups other blocks.
+                "--" => continue,
+                "++++" => {
+                    self.flag(line);
+                    for raw in lines.by_ref().take_while(|l| *l != "++++") {
+                        self.flag(raw);
+                    }
+                    continue;
+                }
+                "|===" => {
+                    let rows: Vec<&str> = lines.by_ref().take_while(|l| *l != "|===").collect();
+                    self.table(&rows, block_attributes.as_deref());
+                    continue;
+                }
+                // A list continuation has no equivalent outside of indented lists.
+                "+" => {
+                    self.flag(line);
+                    continue;
+                }
+                _ => {}
+            }
+
+            if line.starts_with("//")
+                || ATTRIBUTE_ENTRY_REGEX.is_match(line)
+                || CONDITIONAL_REGEX.is_match(line)
+            {
+                continue;
+            }
+
+            if let Some(id) = anchor(line) {
+                // The HTML block needs a blank line after it, so that the next block stays Markdown.
+                self.output.push(format!("<a id=\"{id}\"></a>"));
+                self.output.push(String::new());
+                continue;
+            }
+
+            if let Some(caps) = BLOCK_ATTRIBUTES_REGEX.captures(line) {
+                let attrs = caps[1].to_string();
+                if !is_known_attribute(&attrs) {
+                    self.flag(line);
+                }
+                attributes = Some(attrs);
+                continue;
+            }
+
+            if let Some(caps) = INCLUDE_REGEX.captures(line) {
+                let level = LEVELOFFSET_REGEX
+                    .captures(&caps[2])
+                    .and_then(|level| level[1].parse::<usize>().ok())
+                    .unwrap_or_default();
+                if let Some(module) = included.iter().find(|m| m.file_name() == &caps[1]) {
+                    self.module(module, offset + level);
+                } else {
+                    self.flag(line);
+                }
+                continue;
+            }
+
+            if BLOCK_MACRO_REGEX.is_match(line) {
+                self.flag(line);
+                continue;
+            }
+
+            let converted = if let Some(caps) = HEADING_REGEX.captures(line) {
+                // Markdown only has six levels of headings.
+                let level = (caps[1].len() + offset).min(6);
+                let title = self.inline(&caps[2]);
+                format!("{} {title}\n", "#".repeat(level))
+            } else if let Some(caps) = BLOCK_TITLE_REGEX.captures(line) {
+                // A title that would otherwise form a delimiter starts with `{empty}`.
+                let title = caps[1].strip_prefix("{empty}").unwrap_or(&caps[1]);
+                format!("**{}**\n", self.inline(title))
+            } else if let Some(caps) = ADMONITION_REGEX.captures(line) {
+                format!("> **{}:** {}", title_case(&caps[1]), self.inline(&caps[2]))
+            } else if let Some(caps) = UNORDERED_REGEX.captures(line) {
+                let depth = if &caps[1] == "-" {
+                    0
+                } else {
+                    caps[1].len() - 1
+                };
+                format!("{}- {}", "  ".repeat(depth), self.inline(&caps[2]))
+            } else if let Some(caps) = ORDERED_REGEX.captures(line) {
+                let depth = caps[1].len() - 1;
+                format!("{}1. {}", "   ".repeat(depth), self.inline(&caps[2]))
+            } else if let Some(caps) = DESCRIPTION_REGEX.captures(line) {
+                let term = self.inline(&caps[1]);
+                let description = caps.get(2).map(|d| d.as_str()).unwrap_or_default();
+                format!("- **{term}:** {}", self.inline(description))
+            } else if let Some(start) = line.strip_suffix(" +") {
+                // A hard line break.
+                format!("{}\\", self.inline(start))
+            } else {
+                self.inline(line)
+            };
+
+            self.output.push(converted);
+        }
+    }
+
+    /// Convert the content of a delimited block to a Markdown block quote.
+    fn quote(&mut self, inner: &str, attributes: Option<&str>, offset: usize, included: &[Module]) {
+        let start = self.output.len();
+
+        if let Some(label) =
+            attributes.filter(|attrs| ADMONITION_REGEX.is_match(&format!("{attrs}: ")))
+        {
+            self.output.push(format!("**{}:**", title_case(label)));
+            self.output.push(String::new());
+        }
+        self.text(inner, offset, included);
+
+        for line in &mut self.output[start..] {
+            *line = if line.is_empty() {
+                ">".to_string()
+            } else {
+                format!("> {line}")
+            };
+        }
+    }
+
+    /// Convert a table with one row per line. Other tables are flagged.
+    fn table(&mut self, rows: &[&str], attributes: Option<&str>) {
+        let rows: Vec<&str> = rows.iter().copied().filter(|row| !row.is_empty()).collect();
+        let cells: Vec<Vec<&str>> = rows
+            .iter()
+            .map(|row| {
+                row.strip_prefix('|')
+                    .map(|r| r.split('|').map(str::trim).collect())
+            })
+            .collect::<Option<_>>()
+            .unwrap_or_default();
+        let columns = cells.first().map_or(0, Vec::len);
+
+        if cells.is_empty() || cells.iter().any(|row| row.len() != columns) {
+            self.flag("|===");
+            for row in rows {
+                self.flag(row);
+            }
+            self.flag("|===");
+            return;
+        }
+
+        let has_header = attributes.is_some_and(|attrs| attrs.contains("header"));
+        let mut cells = cells.into_iter();
+        let header = if has_header {
+            cells.next().unwrap_or_default()
+        } else {
+            vec![""; columns]
+        };
+
+        let header: Vec<String> = header.iter().map(|cell| self.inline(cell)).collect();
+        self.output.push(format!("| {} |", header.join(" | ")));
+        self.output.push(format!("|{}", " --- |".repeat(columns)));
+        for row in cells {
+            let row: Vec<String> = row.iter().map(|cell| self.inline(cell)).collect();
+            self.output.push(format!("| {} |", row.join(" | ")));
+        }
+        self.output.push(String::new());
+    }
+
+    /// Convert the inline formatting in a line of text. Code spans stay as they are.
+    fn inline(&mut self, text: &str) -> String {
+        let text = LITERAL_MONOSPACE_REGEX.replace_all(text, "`$1`");
+
+        text.split('`')
+            .enumerate()
+            .map(|(index, segment)| {
+                // Odd segments are inside backticks.
+                if index % 2 == 1 {
+                    segment.to_string()
+                } else {
+                    self.inline_segment(segment)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("`")
+    }
+
+    /// Convert the inline formatting in text outside of code spans.
+    fn inline_segment(&mut self, text: &str) -> String {
+        if INLINE_MACRO_REGEX.is_match(text) || ATTRIBUTE_REFERENCE_REGEX.is_match(text) {
+            self.unconverted.push(text.to_string());
+        }
+
+        let text = LINK_REGEX.replace_all(text, link);
+        let text = URL_REGEX.replace_all(&text, link);
+        let text = XREF_REGEX.replace_all(&text, |caps: &Captures| {
+            // The target can also name the file, such as `file.adoc#anchor`.
+            let id = caps[1].rsplit('#').next().unwrap_or_default().to_string();
+            let label = if caps[2].is_empty() {
+                id.clone()
+            } else {
+                caps[2].to_string()
+            };
+            self.xref(&id, &label)
+        });
+        let text = ANGLE_XREF_REGEX.replace_all(&text, |caps: &Captures| {
+            let id = caps[1].trim().to_string();
+            let label = caps
+                .get(2)
+                .map_or_else(|| id.clone(), |l| l.as_str().to_string());
+            self.xref(&id, &label)
+        });
+        let text = FOOTNOTEREF_REGEX.replace_all(&text, |caps: &Captures| {
+            self.footnote(&caps[1], caps.get(2).map(|t| t.as_str()))
+        });
+        let text = FOOTNOTE_REGEX.replace_all(&text, |caps: &Captures| {
+            let id = if caps[1].is_empty() {
+                format!("{}", self.footnotes.len() + 1)
+            } else {
+                caps[1].to_string()
+            };
+            self.footnote(&id, Some(&caps[2]))
+        });
+
+        // Apply the constrained formatting twice, because neighboring matches share a character.
+        let mut text = text.into_owned();
+        for _ in 0..2 {
+            text = BOLD_REGEX.replace_all(&text, "$1**$2**$3").into_owned();
+        }
+        text = DOUBLE_ITALIC_REGEX.replace_all(&text, "*$1*").into_owned();
+        for _ in 0..2 {
+            text = ITALIC_REGEX.replace_all(&text, "$1*$2*$3").into_owned();
+            text = PASSTHROUGH_REGEX.replace_all(&text, "$1$2$3").into_owned();
+        }
+
+        text
+    }
+
+    /// A Markdown link to an anchor, which can be in another Markdown file.
+    fn xref(&mut self, id: &str, label: &str) -> String {
+        match self.anchors.get(id) {
+            Some(file_name) if file_name == self.file_name => format!("[{label}](#{id})"),
+            Some(file_name) => format!("[{label}]({file_name}#{id})"),
+            None => {
+                self.unconverted
+                    .push(format!("xref to an unknown anchor: {id}"));
+                format!("[{label}](#{id})")
+            }
+        }
+    }
+
+    /// A Markdown footnote reference. Records the footnote so that its text appears
+    /// at the end of the file.
+    fn footnote(&mut self, id: &str, text: Option<&str>) -> String {
+        let text = text.filter(|t| !t.is_empty()).map(ToString::to_string);
+
+        if let Some((_, known)) = self
+            .footnotes
+            .iter_mut()
+            .find(|(known_id, _)| known_id == id)
+        {
+            if known.is_none() {
+                *known = text;
+            }
+        } else {
+            self.footnotes.push((id.to_string(), text));
+        }
+
+        format!("[^{id}]")
+    }
+
+    /// Finish the Markdown file with footnotes, and report the AsciiDoc that
+    /// the converter couldn't convert.
+    fn finish(mut self) -> String {
+        for (id, text) in std::mem::take(&mut self.footnotes) {
+            let text = match (text, self.private_footnote) {
+                (Some(text), _) => text,
+                (None, Some(private)) if id == "PrivateTicketFootnote" => private.to_string(),
+                (None, _) => {
+                    self.unconverted
+                        .push(format!("footnote without text: {id}"));
+                    String::new()
+                }
+            };
+            self.output.push(String::new());
+            self.output.push(format!("[^{id}]: {text}"));
+        }
+
+        if !self.unconverted.is_empty() {
+            self.unconverted.dedup();
+            log::warn!(
+                "Cannot convert some AsciiDoc to Markdown in {}:\n\t{}",
+                self.file_name,
+                self.unconverted.join("\n\t")
+            );
+        }
+
+        // Merge repeated blank lines.
+        let mut text = String::new();
+        let mut previous_blank = true;
+        for line in self.output.iter().flat_map(|l| l.split('\n')) {
+            let blank = line.trim().is_empty();
+            if !(blank && previous_blank) {
+                text.push_str(line.trim_end_matches(' '));
+                text.push('\n');
+            }
+            previous_blank = blank;
+        }
+
+        // End with a single line break, unless the file is empty.
+        text.truncate(text.trim_end().len());
+        if !text.is_empty() {
+            text.push('\n');
+        }
+
+        text
+    }
+}
+
+/// Convert the captures of a link macro to a Markdown link.
+fn link(caps: &Captures) -> String {
+    let url = &caps[1];
+    // The caret only opens the link in a new window.
+    let label = caps[2].trim_end_matches('^');
+
+    if label.is_empty() {
+        format!("<{url}>")
+    } else {
+        format!("[{label}]({url})")
+    }
+}
+
+/// Block attributes that the conversion handles, or that don't affect Markdown.
+fn is_known_attribute(attributes: &str) -> bool {
+    let name = attributes
+        .split(',')
+        .next()
+        .unwrap_or_default()
+        .trim_matches('"');
+
+    matches!(
+        name,
+        "appendix"
+            | "discrete"
+            | "source"
+            | "listing"
+            | "literal"
+            | "subs"
+            | "role"
+            | "NOTE"
+            | "TIP"
+            | "IMPORTANT"
+            | "WARNING"
+            | "CAUTION"
+    ) || name.starts_with("cols=")
+        || name.starts_with("options=")
+        || name.starts_with("role=")
+        || name.starts_with("subs=")
+        || name.starts_with('.')
+}
+
+/// Capitalize only the first letter of an admonition label.
+fn title_case(label: &str) -> String {
+    let lower = label.to_lowercase();
+    let mut chars = lower.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// The text of the private ticket footnote in the project.
+    pub const PRIVATE_FOOTNOTE: &str = "This ticket is not publicly accessible. \
+        Therefore, the release note does not include a link to the ticket.";
+
+    /// Convert a chapter, its included modules, and the appendix, and check the Markdown.
+    #[test]
+    fn convert_to_markdown() {
+        let reference = Module::WithContent {
+            file_name: "ref_bug-fixes-kernel.adoc".into(),
+            text: r#"[id="bug-fixes-kernel"]
+= Kernel
+
+ifndef::fn-private[]
+:fn-private: example
+endif::[]
+
+[id="Jira-A-1"]
+.A fixed bug
+
+The `+--force+` option *no longer* fails. See link:https://docs.host.org[the _docs_].
+
+link:https://jira.host.org/browse/A-1[Jira:A-1]
+
+[id="Jira-A-2"]
+.A private bug
+
+NOTE: Reboot.
+
+[source,bash]
+----
+$ reboot
+----
+
+Jira:A-2footnoteref:[PrivateTicketFootnote]
+"#
+            .into(),
+            included_modules: None,
+        };
+        let chapter = Module::WithContent {
+            file_name: "assembly_bug-fixes.adoc".into(),
+            text: r#"[id="bug-fixes"]
+= Bug fixes
+
+This part describes bug fixes.
+
+include::ref_bug-fixes-kernel.adoc[leveloffset=+1]
+"#
+            .into(),
+            included_modules: Some(vec![reference]),
+        };
+        let summary = r#"[appendix]
+= List of tickets by component
+
+[cols="1,2", options="header"]
+|===
+| Component | Tickets
+| kernel | xref:Jira-A-1[Jira:A-1]
+|===
+"#;
+
+        let mut anchors = HashMap::new();
+        collect_anchors(&chapter, "assembly_bug-fixes.md", &mut anchors);
+
+        let mut converter =
+            Converter::new("assembly_bug-fixes.md", &anchors, Some(PRIVATE_FOOTNOTE));
+        converter.module(&chapter, 0);
+        assert!(converter.unconverted.is_empty());
+        assert_eq!(
+            converter.finish(),
+            r#"<a id="bug-fixes"></a>
+
+# Bug fixes
+
+This part describes bug fixes.
+
+<a id="bug-fixes-kernel"></a>
+
+## Kernel
+
+<a id="Jira-A-1"></a>
+
+**A fixed bug**
+
+The `--force` option **no longer** fails. See [the *docs*](https://docs.host.org).
+
+[Jira:A-1](https://jira.host.org/browse/A-1)
+
+<a id="Jira-A-2"></a>
+
+**A private bug**
+
+> **Note:** Reboot.
+
+```bash
+$ reboot
+```
+
+Jira:A-2[^PrivateTicketFootnote]
+
+[^PrivateTicketFootnote]: This ticket is not publicly accessible. Therefore, the release note does not include a link to the ticket.
+"#
+        );
+
+        let mut converter = Converter::new(SUMMARY_FILE, &anchors, None);
+        converter.text(summary, 0, &[]);
+        assert_eq!(
+            converter.finish(),
+            "# List of tickets by component
+
+| Component | Tickets |
+| --- | --- |
+| kernel | [Jira:A-1](assembly_bug-fixes.md#Jira-A-1) |
+"
+        );
+    }
+
+    /// Titles generated from ticket summaries keep their leading dots.
+    #[test]
+    fn convert_block_titles() {
+        let anchors = HashMap::new();
+        let mut converter = Converter::new("index.md", &anchors, None);
+        converter.text("..NET support\n\n.{empty}...\n", 0, &[]);
+        assert!(converter.unconverted.is_empty());
+        assert_eq!(converter.finish(), "**.NET support**\n\n**...**\n");
+    }
+}
@@ -0,0 +1,550 @@
+/*
+acorns: Generate an AsciiDoc release notes document from tracking tickets.
+Copyright (C) 2023  Marek Suchánek  <msuchane@redhat.com>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Conversion of doc text that engineers write in other markup languages to AsciiDoc.
+//!
+//! Each tracker instance configures the markup language of its doc text field.
+//! Acorns converts the doc text to AsciiDoc as soon as it reads the ticket,
+//! so that both the release notes and the status checks work with AsciiDoc.
+
+use once_cell::sync::Lazy;
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use regex::{Captures, Regex};
+
+use crate::config::tracker::DocTextFormat;
+use crate::REGEX_ERROR;
+
+/// A Jira heading, such as `h3. Title`.
+static JIRA_HEADING_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^h[1-6]\.\s+(.*)$").expect(REGEX_ERROR));
+/// The start or the end of a Jira block macro, such as `{code:java}` or `{quote}`.
+static JIRA_BLOCK_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\{(code|noformat|quote|panel|note|info|tip|warning)(?::([^}]*))?\}(.*)$")
+        .expect(REGEX_ERROR)
+});
+/// A Jira list item, such as `* Item`, `## Nested item`, or `#* Mixed item`.
+static JIRA_LIST_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^([*#]+)\s+(.*)$").expect(REGEX_ERROR));
+/// A Jira block quote on a single line.
+static JIRA_BQ_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^bq\.\s+(.*)$").expect(REGEX_ERROR));
+/// A Jira link with text, such as `[text|https://example.com]`.
+static JIRA_LINK_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\[([^|\]\[]+)\|([^\]\[\s]+)\]").expect(REGEX_ERROR));
+/// A Jira link without text, such as `[https://example.com]`.
+static JIRA_BARE_LINK_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\[((?:https?|ftp|mailto):[^\]\[\s]+)\]").expect(REGEX_ERROR));
+/// A mention of a Jira user, such as `[~jdoe]`.
+static JIRA_MENTION_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\[~([^\]]+)\]").expect(REGEX_ERROR));
+/// Jira monospace text, such as `{{text}}`.
+static JIRA_MONOSPACE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{(.+?)\}\}").expect(REGEX_ERROR));
+/// Jira strikethrough text, such as `-text-`.
+static JIRA_STRIKE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(^|\s)-([^\s-](?:[^-]*[^\s-])?)-($|[\s.,;:!?])").expect(REGEX_ERROR));
+/// Jira underlined text, such as `+text+`.
+static JIRA_UNDERLINE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(^|\s)\+([^\s+](?:[^+]*[^\s+])?)\+($|[\s.,;:!?])").expect(REGEX_ERROR)
+});
+/// A Jira citation, such as `??text??`.
+static JIRA_CITATION_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\?\?(.+?)\?\?").expect(REGEX_ERROR));
+/// Jira colored text, such as `{color:red}text{color}`. Only the text remains.
+static JIRA_COLOR_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{color(?::[^}]*)?\}").expect(REGEX_ERROR));
+
+/// Convert the doc text from its configured markup language to AsciiDoc.
+pub fn to_asciidoc(doc_text: &str, format: DocTextFormat) -> String {
+    match format {
+        DocTextFormat::Asciidoc => doc_text.to_string(),
+        DocTextFormat::JiraWiki => from_jira_wiki(doc_text),
+        DocTextFormat::Markdown => from_markdown(doc_text),
+    }
+}
+
+/// Convert Jira wiki markup to AsciiDoc, line by line.
+fn from_jira_wiki(text: &str) -> String {
+    let mut output: Vec<String> = Vec::new();
+    let mut lines = text.lines().peekable();
+    let mut in_list = false;
+
+    while let Some(line) = lines.next() {
+        let line = line.trim_end();
+
+        // A list needs an empty line before and after it, so that AsciiDoc
+        // doesn't join it with the paragraphs around it.
+        let is_list_item = JIRA_LIST_REGEX.is_match(line);
+        if is_list_item != in_list && !line.is_empty() {
+            separate(&mut output);
+        }
+        in_list = is_list_item;
+
+        if let Some(caps) = JIRA_BLOCK_REGEX.captures(line) {
+            let macro_name = caps[1].to_string();
+            let parameters = caps.get(2).map_or("", |p| p.as_str());
+            let end = format!("{{{macro_name}}}");
+
+            // The block can start right after the macro, and end on the same line.
+            let mut content: Vec<String> = Vec::new();
+            let mut rest = caps[3].to_string();
+            loop {
+                if let Some(position) = rest.find(&end) {
+                    content.push(rest[..position].to_string());
+                    break;
+                }
+                content.push(rest);
+                match lines.next() {
+                    Some(next) => rest = next.to_string(),
+                    None => break,
+                }
+            }
+            // Drop the empty lines that the macros leave at the edges of the block.
+            while content.first().is_some_and(|l| l.trim().is_empty()) {
+                content.remove(0);
+            }
+            while content.last().is_some_and(|l| l.trim().is_empty()) {
+                content.pop();
+            }
+
+            separate(&mut output);
+            output.extend(jira_block(&macro_name, parameters, &content));
+            continue;
+        }
+
+        if line.starts_with('|') {
+            // Collect the whole table.
+            let mut rows = vec![line.to_string()];
+            while let Some(next) = lines.next_if(|l| l.trim_start().starts_with('|')) {
+                rows.push(next.trim().to_string());
+            }
+            separate(&mut output);
+            output.extend(jira_table(&rows));
+            continue;
+        }
+
+        let converted = if let Some(caps) = JIRA_HEADING_REGEX.captures(line) {
+            // Headings would break the structure of the document. Use block titles instead.
+            format!(".{}", jira_inline(&caps[1]))
+        } else if let Some(caps) = JIRA_BQ_REGEX.captures(line) {
+            separate(&mut output);
+            format!("____\n{}\n____", jira_inline(&caps[1]))
+        } else if let Some(caps) = JIRA_LIST_REGEX.captures(line) {
+            // The last character of the marker decides the type of the nested list.
+            let depth = caps[1].len();
+            let marker = if caps[1].ends_with('#') { "." } else { "*" };
+            format!("{} {}", marker.repeat(depth), jira_inline(&caps[2]))
+        } else if line == "----" {
+            "'''".to_string()
+        } else {
+            jira_inline(line)
+        };
+
+        output.push(converted);
+    }
+
+    output.join("\n")
+}
+
+/// Start a new block with an empty line, unless the previous line is already empty.
+fn separate(output: &mut Vec<String>) {
+    if output.last().is_some_and(|line| !line.is_empty()) {
+        output.push(String::new());
+    }
+}
+
+/// Convert the content of a Jira block macro to an AsciiDoc block.
+fn jira_block(macro_name: &str, parameters: &str, content: &[String]) -> Vec<String> {
+    let mut block = Vec::new();
+
+    match macro_name {
+        "code" => {
+            // The language is the only parameter without a name, such as `{code:java|title=...}`.
+            let language = parameters
+                .split('|')
+                .find(|p| !p.contains('=') && !p.is_empty());
+            if let Some(language) = language {
+                block.push(format!("[source,{language}]"));
+            }
+            block.push("----".to_string());
+            block.extend(content.iter().cloned());
+            block.push("----".to_string());
+        }
+        "noformat" => {
+            block.push("....".to_string());
+            block.extend(content.iter().cloned());
+            block.push("....".to_string());
+        }
+        "quote" | "panel" => {
+            let delimiter = if macro_name == "quote" {
+                "____"
+            } else {
+                "****"
+            };
+            block.push(delimiter.to_string());
+            block.extend(content.iter().map(|line| jira_inline(line)));
+            block.push(delimiter.to_string());
+        }
+        _ => {
+            let admonition = match macro_name {
+                "warning" => "WARNING",
+                "tip" => "TIP",
+                _ => "NOTE",
+            };
+            block.push(format!("[{admonition}]"));
+            block.push("====".to_string());
+            block.extend(content.iter().map(|line| jira_inline(line)));
+            block.push("====".to_string());
+        }
+    }
+
+    // Separate the block from the following paragraph.
+    block.push(String::new());
+
+    block
+}
+
+/// Convert the rows of a Jira table to an AsciiDoc table.
+/// Header cells start with `||`. Regular cells start with `|`.
+fn jira_table(rows: &[String]) -> Vec<String> {
+    let has_header = rows.first().is_some_and(|row| row.starts_with("||"));
+
+    let mut table = Vec::new();
+    if has_header {
+        table.push("[options=\"header\"]".to_string());
+    }
+    table.push("|===".to_string());
+
+    for row in rows {
+        let cells: Vec<String> = table_cells(row)
+            .into_iter()
+            .map(str::trim)
+            .filter(|cell| !cell.is_empty())
+            // A `|` that stays in the cell must not end the AsciiDoc cell.
+            .map(|cell| format!("|{}", jira_inline(cell).replace('|', "\\|")))
+            .collect();
+        table.push(cells.join(" "));
+    }
+
+    table.push("|===".to_string());
+    table.push(String::new());
+
+    table
+}
+
+/// Split a row of a Jira table on the cell separators. A `|` inside brackets or braces,
+/// such as in `[docs|https://example.com]`, belongs to the cell.
+fn table_cells(row: &str) -> Vec<&str> {
+    let mut cells = Vec::new();
+    let mut depth = 0_usize;
+    let mut start = 0;
+
+    for (index, c) in row.char_indices() {
+        match c {
+            '[' | '{' => depth += 1,
+            ']' | '}' => depth = depth.saturating_sub(1),
+            '|' if depth == 0 => {
+                cells.push(&row[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    cells.push(&row[start..]);
+
+    cells
+}
+
+/// Convert the inline Jira markup in a line of text.
+/// Bold, `*text*`, and italic, `_text_`, are the same in AsciiDoc.
+fn jira_inline(text: &str) -> String {
+    let text = JIRA_COLOR_REGEX.replace_all(text, "");
+    let text = JIRA_MONOSPACE_REGEX.replace_all(&text, "`+$1+`");
+    let text = JIRA_LINK_REGEX.replace_all(&text, |caps: &Captures| {
+        let url = &caps[2];
+        let label = &caps[1];
+        if let Some(anchor) = url.strip_prefix('#') {
+            format!("<<{anchor},{label}>>")
+        } else {
+            format!("link:{url}[{label}]")
+        }
+    });
+    let text = JIRA_BARE_LINK_REGEX.replace_all(&text, "$1[]");
+    let text = JIRA_MENTION_REGEX.replace_all(&text, "$1");
+    let text = JIRA_STRIKE_REGEX.replace_all(&text, "$1[line-through]#$2#$3");
+    let text = JIRA_UNDERLINE_REGEX.replace_all(&text, "$1[underline]#$2#$3");
+    let text = JIRA_CITATION_REGEX.replace_all(&text, "_${1}_");
+
+    // A forced line break.
+    text.replace(r"\\", " +\n")
+}
+
+/// Convert CommonMark to AsciiDoc.
+fn from_markdown(text: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    let mut writer = AsciidocWriter::default();
+
+    for event in Parser::new_ext(text, options) {
+        writer.event(event);
+    }
+
+    writer.out.trim_end().to_string()
+}
+
+/// The conversion of Markdown events to AsciiDoc.
+#[derive(Default)]
+struct AsciidocWriter {
+    out: String,
+    /// The markers of the lists that are currently open: `*` or `.`.
+    lists: Vec<char>,
+    /// The delimiter of the code block that is currently open.
+    code_delimiter: &'static str,
+    /// The number of cells in the current table row.
+    cells: usize,
+}
+
+impl AsciidocWriter {
+    /// End the current block with an empty line, unless it already ends.
+    fn end_block(&mut self) {
+        while !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn event(&mut self, event: Event) {
+        match event {
+            // Headings would break the structure of the document. Use block titles instead.
+            Event::Start(Tag::Heading { .. }) => {
+                self.end_block();
+                self.out.push('.');
+            }
+            // Paragraphs in list items continue the item.
+            Event::Start(Tag::Paragraph) if self.lists.is_empty() => self.end_block(),
+            Event::End(TagEnd::Paragraph) => {
+                if self.lists.is_empty() {
+                    self.end_block();
+                } else {
+                    self.out.push('\n');
+                }
+            }
+            Event::Start(Tag::BlockQuote(_)) => {
+                self.end_block();
+                self.out.push_str("____\n");
+            }
+            Event::End(TagEnd::BlockQuote(_)) => {
+                self.out.truncate(self.out.trim_end().len());
+                self.out.push_str("\n____\n\n");
+            }
+            Event::Start(Tag::CodeBlock(kind)) => {
+                self.end_block();
+                // An indented code block has no language, and becomes a literal block.
+                self.code_delimiter = match kind {
+                    CodeBlockKind::Fenced(language) => {
+                        if !language.is_empty() {
+                            self.out.push_str("[source,");
+                            self.out.push_str(&language);
+                            self.out.push_str("]\n");
+                        }
+                        "----"
+                    }
+                    CodeBlockKind::Indented => "....",
+                };
+                self.out.push_str(self.code_delimiter);
+                self.out.push('\n');
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if !self.out.ends_with('\n') {
+                    self.out.push('\n');
+                }
+                self.out.push_str(self.code_delimiter);
+                self.out.push_str("\n\n");
+            }
+            Event::Start(Tag::List(start)) => {
+                if self.lists.is_empty() {
+                    self.end_block();
+                } else if !self.out.ends_with('\n') {
+                    self.out.push('\n');
+                }
+                self.lists.push(if start.is_some() { '.' } else { '*' });
+            }
+            Event::End(TagEnd::List(_)) => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.end_block();
+                }
+            }
+            Event::Start(Tag::Item) => {
+                let marker = self.lists.last().copied().unwrap_or('*');
+                let depth = self.lists.len();
+                self.out.push_str(&marker.to_string().repeat(depth));
+                self.out.push(' ');
+            }
+            Event::End(TagEnd::Item) if !self.out.ends_with('\n') => self.out.push('\n'),
+            Event::Start(Tag::Table(_)) => {
+                self.end_block();
+                self.out.push_str("[options=\"header\"]\n|===\n");
+            }
+            Event::End(TagEnd::Table) => self.out.push_str("|===\n\n"),
+            Event::Start(Tag::TableHead | Tag::TableRow) => self.cells = 0,
+            Event::End(TagEnd::Heading(_) | TagEnd::TableHead | TagEnd::TableRow) => {
+                self.out.push('\n');
+            }
+            Event::Start(Tag::TableCell) => {
+                if self.cells > 0 {
+                    self.out.push(' ');
+                }
+                self.out.push('|');
+                self.cells += 1;
+            }
+            Event::Start(Tag::Emphasis) | Event::End(TagEnd::Emphasis) => self.out.push_str("__"),
+            Event::Start(Tag::Strong) | Event::End(TagEnd::Strong) => self.out.push_str("**"),
+            Event::Start(Tag::Strikethrough) => self.out.push_str("[line-through]#"),
+            Event::End(TagEnd::Strikethrough) => self.out.push('#'),
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                self.out.push_str("link:");
+                self.out.push_str(&dest_url);
+                self.out.push('[');
+            }
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                self.out.push_str("image:");
+                self.out.push_str(&dest_url);
+                self.out.push('[');
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => self.out.push(']'),
+            Event::Text(text) | Event::Html(text) | Event::InlineHtml(text) => {
+                self.out.push_str(&text);
+            }
+            Event::Code(code) => {
+                self.out.push_str("`+");
+                self.out.push_str(&code);
+                self.out.push_str("+`");
+            }
+            Event::SoftBreak => self.out.push('\n'),
+            Event::HardBreak => self.out.push_str(" +\n"),
+            Event::Rule => {
+                self.end_block();
+                self.out.push_str("'''\n\n");
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_jira_wiki() {
+        let doc_text = r"h3. Faster *boot* with {{systemd}}
+
+Previously, the [boot loader|https://example.com/boot] stopped.\\Now it -fails- works:
+* Step one
+## Nested step
+{code:bash}
+$ reboot
+{code}
+||Option||Value||
+|timeout|5|
+{note}Reboot twice.{note}";
+
+        assert_eq!(
+            to_asciidoc(doc_text, DocTextFormat::JiraWiki),
+            r#".Faster *boot* with `+systemd+`
+
+Previously, the link:https://example.com/boot[boot loader] stopped. +
+Now it [line-through]#fails# works:
+
+* Step one
+.. Nested step
+
+[source,bash]
+----
+$ reboot
+----
+
+[options="header"]
+|===
+|Option |Value
+|timeout |5
+|===
+
+[NOTE]
+====
+Reboot twice.
+====
+"#
+        );
+    }
+
+    /// Links and other markup with a `|` stay in one table cell.
+    #[test]
+    fn convert_jira_table_with_links() {
+        let doc_text = "||Option||Documentation||
+|timeout|[docs|https://example.com/docs]|
+|owner|[~jdoe] sets {{a|b}}|";
+
+        assert_eq!(
+            to_asciidoc(doc_text, DocTextFormat::JiraWiki),
+            r#"[options="header"]
+|===
+|Option |Documentation
+|timeout |link:https://example.com/docs[docs]
+|owner |jdoe sets `+a\|b+`
+|===
+"#
+        );
+    }
+
+    #[test]
+    fn convert_markdown() {
+        let doc_text = "# Faster boot
+
+Previously, the [boot loader](https://example.com/boot) *stopped*.
+Now it **works** with `systemd`:
+
+1. Step one
+   * Nested step
+
+```bash
+$ reboot
+```";
+
+        assert_eq!(
+            to_asciidoc(doc_text, DocTextFormat::Markdown),
+            ".Faster boot
+
+Previously, the link:https://example.com/boot[boot loader] __stopped__.
+Now it **works** with `+systemd+`:
+
+. Step one
+** Nested step
+
+[source,bash]
+----
+$ reboot
+----"
+        );
+    }
+
+    #[test]
+    fn keep_asciidoc() {
+        let doc_text = ".Title\n\nText with *bold* and {{braces}}.";
+        assert_eq!(to_asciidoc(doc_text, DocTextFormat::Asciidoc), doc_text);
+    }
+}
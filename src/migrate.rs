@@ -0,0 +1,227 @@
+/*
+acorns: Generate an AsciiDoc release notes document from tracking tickets.
+Copyright (C) 2024  Marek Suchánek  <msuchane@redhat.com>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+/*!
+A subcommand that migrates a release notes project from the legacy `cizrna/` data directory
+to the current `acorns/` data directory.
+
+It renames the directory and rewrites the paths to it in include directives
+and attribute entries in the AsciiDoc files of the project.
+By default, the subcommand only displays the changes as a dry run.
+*/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{bail, Result, WrapErr};
+use ignore::Walk;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::config::{DATA_PREFIX, LEGACY_NAME};
+use crate::footnote::is_file_adoc;
+use crate::REGEX_ERROR;
+
+/// This regex matches the legacy directory as a path component,
+/// either at the start of the path or after a separator.
+static LEGACY_PATH_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(&format!(r"(^|[\s/:=\[{{]){LEGACY_NAME}/")).expect(REGEX_ERROR));
+
+/// This regex matches an AsciiDoc attribute entry, such as `:generated-dir: cizrna/generated`.
+static ATTRIBUTE_ENTRY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^:!?[\w-]+!?:").expect(REGEX_ERROR));
+
+/// An AsciiDoc file that needs its paths rewritten.
+struct Rewrite {
+    path: PathBuf,
+    /// The changed lines: the line number, the old line, and the new line.
+    changes: Vec<(usize, String, String)>,
+    /// The complete new content of the file.
+    new_text: String,
+}
+
+/// Migrate the legacy data directory in the project to the current one.
+///
+/// If `apply` is `false`, only display the changes that the migration would make.
+pub fn migrate(project: &Path, apply: bool) -> Result<()> {
+    let abs_path = project.canonicalize()?;
+    let legacy_dir = abs_path.join(LEGACY_NAME);
+    let data_dir = abs_path.join(DATA_PREFIX);
+
+    if !legacy_dir.is_dir() {
+        bail!(
+            "The legacy `{}/` directory is missing in {}.",
+            LEGACY_NAME,
+            abs_path.display()
+        );
+    }
+    if data_dir.exists() {
+        bail!(
+            "The `{}/` directory already exists in {}. Resolve the conflict manually.",
+            DATA_PREFIX,
+            abs_path.display()
+        );
+    }
+
+    let rewrites = find_rewrites(&abs_path)?;
+
+    // Display the changes as a simple diff.
+    println!("rename {LEGACY_NAME}/ => {DATA_PREFIX}/");
+    for rewrite in &rewrites {
+        let rel_path = rewrite
+            .path
+            .strip_prefix(&abs_path)
+            .unwrap_or(&rewrite.path);
+        println!("--- {}\n+++ {}", rel_path.display(), rel_path.display());
+        for (number, old, new) in &rewrite.changes {
+            println!("@@ line {number} @@\n-{old}\n+{new}");
+        }
+    }
+
+    if !apply {
+        log::info!("This is a dry run. To migrate the project, add the `--apply` option.");
+        return Ok(());
+    }
+
+    // Rewrite the files before renaming the directory, because the paths to files
+    // inside the legacy directory change after renaming.
+    for rewrite in &rewrites {
+        log::debug!("Writing file: {}", rewrite.path.display());
+        fs::write(&rewrite.path, &rewrite.new_text)
+            .wrap_err("Failed to rewrite an AsciiDoc file.")?;
+    }
+
+    fs::rename(&legacy_dir, &data_dir).wrap_err("Failed to rename the legacy directory.")?;
+
+    log::info!(
+        "Migrated the project. Rewrote {} AsciiDoc files.",
+        rewrites.len()
+    );
+
+    Ok(())
+}
+
+/// Find all AsciiDoc files in the project that refer to the legacy directory,
+/// and prepare their new content.
+fn find_rewrites(project: &Path) -> Result<Vec<Rewrite>> {
+    let mut rewrites = Vec::new();
+
+    for result in Walk::new(project) {
+        // Each item yielded by the iterator is either a directory entry or an error.
+        let dir_entry = result?;
+        let file_path = dir_entry.path();
+
+        if !is_file_adoc(file_path) {
+            continue;
+        }
+
+        let text = fs::read_to_string(file_path)
+            .wrap_err("Cannot read AsciiDoc file in the project repository.")?;
+
+        let (changes, new_text) = rewrite_text(&text);
+
+        if !changes.is_empty() {
+            rewrites.push(Rewrite {
+                path: file_path.to_path_buf(),
+                changes,
+                new_text,
+            });
+        }
+    }
+
+    Ok(rewrites)
+}
+
+/// Rewrite each line of the file. Returns the changed lines with their line numbers,
+/// and the new text. Each line keeps its original line ending, `\n` or `\r\n`.
+fn rewrite_text(text: &str) -> (Vec<(usize, String, String)>, String) {
+    let mut changes = Vec::new();
+    let mut new_text = String::with_capacity(text.len());
+
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        let content = line.strip_suffix('\n').unwrap_or(line);
+        let content = content.strip_suffix('\r').unwrap_or(content);
+        let ending = &line[content.len()..];
+
+        let new_line = rewrite_line(content);
+        if new_line != content {
+            changes.push((index + 1, content.to_string(), new_line.clone()));
+        }
+        new_text.push_str(&new_line);
+        new_text.push_str(ending);
+    }
+
+    (changes, new_text)
+}
+
+/// Replace the legacy directory with the current one in an include directive
+/// or an attribute entry. Leave other lines unchanged.
+fn rewrite_line(line: &str) -> String {
+    let is_include = line.starts_with("include::");
+    let is_attribute = ATTRIBUTE_ENTRY_REGEX.is_match(line);
+
+    if is_include || is_attribute {
+        LEGACY_PATH_REGEX
+            .replace_all(line, format!("${{1}}{DATA_PREFIX}/"))
+            .into_owned()
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrite_legacy_paths() {
+        assert_eq!(
+            rewrite_line("include::cizrna/generated/internal/ref_bug-fixes.adoc[]"),
+            "include::acorns/generated/internal/ref_bug-fixes.adoc[]"
+        );
+        assert_eq!(
+            rewrite_line(":generated-dir: ../cizrna/generated/external"),
+            ":generated-dir: ../acorns/generated/external"
+        );
+        // Prose that mentions the directory stays unchanged.
+        assert_eq!(
+            rewrite_line("The cizrna/ directory is legacy."),
+            "The cizrna/ directory is legacy."
+        );
+        // Only whole path components change.
+        assert_eq!(
+            rewrite_line("include::mycizrna/file.adoc[]"),
+            "include::mycizrna/file.adoc[]"
+        );
+    }
+
+    #[test]
+    fn keep_line_endings() {
+        let text =
+            "include::cizrna/generated/ref_a.adoc[]\r\nText.\r\n\ninclude::cizrna/ref_b.adoc[]";
+        let (changes, new_text) = rewrite_text(text);
+
+        assert_eq!(
+            new_text,
+            "include::acorns/generated/ref_a.adoc[]\r\nText.\r\n\ninclude::acorns/ref_b.adoc[]"
+        );
+        let lines: Vec<usize> = changes.iter().map(|(line, _, _)| *line).collect();
+        assert_eq!(lines, [1, 4]);
+        assert_eq!(changes[0].1, "include::cizrna/generated/ref_a.adoc[]");
+    }
+}
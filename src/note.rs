@@ -16,17 +16,35 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::config::Cleanup;
 use crate::templating::DocumentVariant;
 use crate::ticket_abstraction::AbstractTicket;
+use crate::REGEX_ERROR;
+
+/// A block title, such as `.Title`, which is not an ordered list item, such as `. Item`.
+/// The title itself can start with one dot, such as `..NET support`.
+/// It can start with spaces, which the status table reports as an error of its own.
+static TITLE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^ *\.\.?[^.\s]").expect(REGEX_ERROR));
 
 impl AbstractTicket {
     /// Compose a release note from an abstract ticket.
+    ///
+    /// If the release note already appeared earlier in the document, `repeated_in`
+    /// is the ID of the module that includes this repeated copy.
     #[must_use]
-    pub fn release_note(&self, variant: DocumentVariant, with_priv_footnote: bool) -> String {
-        let anchor = self.anchor_declaration();
+    pub fn release_note(
+        &self,
+        variant: DocumentVariant,
+        with_priv_footnote: bool,
+        repeated_in: Option<&str>,
+    ) -> String {
+        let anchor = self.anchor_declaration(repeated_in);
 
         // This debug information line appears at empty release notes
-        // and everywhere in the Internal document variant.
+        // and everywhere in the variants with debugging information, such as Internal.
         let debug_info = format!(
             "| {} | {} | link:{}[]",
             &self.docs_contact, self.doc_text_status, &self.url
@@ -52,8 +70,8 @@ impl AbstractTicket {
                 anchor,
                 doc_text_unix,
                 self.all_signatures(with_priv_footnote),
-                // In the internal variant, add the debug information line.
-                if variant == DocumentVariant::Internal {
+                // In the variants with debugging information, add the debug information line.
+                if variant.debug_info() {
                     &debug_info
                 } else {
                     ""
@@ -108,20 +126,64 @@ impl AbstractTicket {
         let service = self.id.tracker.short_name();
         let key = &self.id.key;
 
-        // TODO: This anchor isn't unique across the document if the RN is reused.
         format!("{service}-{key}")
     }
 
-    /// Format an AsciiDoc ID line that sets an HTML anchor.
+    /// Format the anchor of one copy of the release note.
+    /// The primary, first copy of the release note uses the plain anchor,
+    /// so that references such as `xref` point to it. Repeated copies append
+    /// the ID of the module that includes them, which keeps them unique in the document.
     ///
-    /// For example, `[id="BZ-12345"]`.
-    fn anchor_declaration(&self) -> String {
+    /// For example, `BZ-12345`, or `BZ-12345_known-issues` for a repeated copy.
+    #[must_use]
+    pub fn copy_anchor(&self, repeated_in: Option<&str>) -> String {
         let anchor = self.anchor();
 
-        format!("[id=\"{anchor}\"]")
+        if let Some(module_id) = repeated_in {
+            format!("{anchor}_{module_id}")
+        } else {
+            anchor
+        }
+    }
+
+    /// Format an AsciiDoc ID line that sets the HTML anchor of this copy of the release note.
+    ///
+    /// For example, `[id="BZ-12345"]`.
+    fn anchor_declaration(&self, repeated_in: Option<&str>) -> String {
+        format!("[id=\"{}\"]", self.copy_anchor(repeated_in))
+    }
+
+    /// If the doc text has no title, add the ticket summary as the title.
+    /// The optional cleanup removes parts of the summary that don't belong in the title.
+    /// An empty doc text stays empty, so that it still counts as a missing release note.
+    /// If nothing remains of the summary, the doc text stays without a title.
+    pub fn add_title_from_summary(&mut self, cleanup: Option<&Cleanup>) {
+        if content_lines(&self.doc_text).is_empty() || has_title(&self.doc_text) {
+            return;
+        }
+
+        let summary = match cleanup {
+            Some(cleanup) => cleanup.apply(&self.summary),
+            None => self.summary.clone(),
+        };
+        let title = summary.trim();
+
+        if title.is_empty() {
+            return;
+        }
+
+        let mut title_line = format!(".{title}");
+        // More leading dots would turn the title into a different AsciiDoc element,
+        // such as the `....` block delimiter.
+        if !TITLE_REGEX.is_match(&title_line) {
+            title_line = format!(".{{empty}}{title}");
+        }
+
+        self.doc_text = format!("{title_line}\n\n{}", self.doc_text.trim_start());
     }
 
     /// Format a reference using the xref syntax that points back to this release note.
+    /// It points to the primary copy if the release note appears several times.
     #[must_use]
     pub fn xref(&self) -> String {
         let anchor = self.anchor();
@@ -131,6 +193,13 @@ impl AbstractTicket {
     }
 }
 
+/// Check whether the doc text starts with a title in the AsciiDoc block title format, `.Title`.
+fn has_title(doc_text: &str) -> bool {
+    content_lines(doc_text)
+        .first()
+        .is_some_and(|line| TITLE_REGEX.is_match(line))
+}
+
 /// Pull out the lines from a doc text that aren't empty and aren't comments.
 /// In other words, this should be the actual text content of the release note.
 pub fn content_lines(doc_text: &str) -> Vec<&str> {
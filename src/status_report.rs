@@ -31,21 +31,13 @@ use regex::Regex;
 use serde::Serialize;
 use time::{format_description::well_known::Rfc2822, OffsetDateTime};
 
+use crate::config::{CheckLevel, StatusRules};
+use crate::custom_templates::{self, CustomTemplates};
 use crate::extra_fields::DocTextStatus;
 use crate::note::content_lines;
 use crate::ticket_abstraction::AbstractTicket;
 use crate::REGEX_ERROR;
 
-/// These doc types don't belong to any particular target release.
-/// Skip the release check for these.
-const UNCHECKED_DOC_TYPES: [&str; 3] = [
-    "known issue",
-    "technology preview",
-    "deprecated functionality",
-];
-/// The maximum allowed title length for a release note.
-const MAX_TITLE_LENGTH: usize = 120;
-
 /// A regular expression to extract a version number in the x.y.z format.
 static VERSION_XYZ_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"(\d+)\.(\d+)\.(\d+)").expect(REGEX_ERROR));
@@ -235,7 +227,7 @@ impl Checks {
         let warnings: Vec<&str> = items
             .iter()
             .filter_map(|status| match status {
-                Status::Error(e) => Some(e.as_str()),
+                Status::Warning(w) => Some(w.as_str()),
                 _ => None,
             })
             .collect();
@@ -285,32 +277,45 @@ impl Status {
         }
     }
 
+    /// Change the severity of a failed check to the level configured for the check.
+    fn at_level(self, level: CheckLevel) -> Self {
+        match (self, level) {
+            (Self::Ok, _) | (_, CheckLevel::Off) => Self::Ok,
+            (Self::Warning(message) | Self::Error(message), CheckLevel::Warning) => {
+                Self::Warning(message)
+            }
+            (Self::Warning(message) | Self::Error(message), CheckLevel::Error) => {
+                Self::Error(message)
+            }
+        }
+    }
+
     // TODO: Consider comparing the doc text with the predefined Bugzilla doc text templates,
     // if Jira also implements them in some way.
     /// Analyze the doc text and check if it conforms to a general release note format.
-    fn from_text(text: &str) -> Self {
+    fn from_text(text: &str, rules: &StatusRules) -> Self {
         let content_lines = content_lines(text);
 
         match content_lines.len() {
             // If the doc text contains too few paragraphs, return with an error.
-            0 => Self::Error("Empty RN.".into()),
-            // TODO: If the project configuration auto-generates titles, release notes
-            // can normally have just one paragraph. Revisit when the option is available.
-            1 => Self::Error("Text in one paragraph.".into()),
+            0 => Self::Error("Empty RN.".into()).at_level(rules.checks.text),
+            // If the project generates titles from the summary, the generated title
+            // already precedes a single paragraph here, so the release note passes.
+            1 => Self::Error("Text in one paragraph.".into()).at_level(rules.checks.text),
             _ => {
                 // If the doc text contains at least two paragraphs, it can be a release note.
                 // In that case, proceed with the analysis.
                 // It's now safe to index directly into the list, because it contains at least 2 items.
                 // Use this to analyze the release note title in detail.
                 let first_content_line = content_lines[0];
-                Self::from_title(first_content_line)
+                Self::from_title(first_content_line, rules)
             }
         }
     }
 
     /// Check that the first line in a release note is a title
     /// in the AsciiDoc label format, and that it matches other title requirements.
-    fn from_title(text: &str) -> Self {
+    fn from_title(text: &str, rules: &StatusRules) -> Self {
         // Identify the title as a line that starts with a dot (`.`) followed by a character,
         // and capture everything after the dot for analysis.
         // Also match if the line starts with spaces and then such a title,
@@ -330,31 +335,35 @@ impl Status {
 
             // Report leading spaces.
             if text.starts_with(' ') {
-                Self::Error("Title starts with a space.".into())
+                Self::Error("Title starts with a space.".into()).at_level(rules.checks.text)
             // Report a long title.
-            } else if length > MAX_TITLE_LENGTH {
+            } else if length > rules.max_title_length {
                 Self::Warning(format!("Long title: {length} characters."))
+                    .at_level(rules.checks.title_length)
             } else {
                 Self::Ok
             }
         } else {
-            Self::Error("Missing title.".into())
+            Self::Error("Missing title.".into()).at_level(rules.checks.text)
         }
     }
 
     /// Report when the bug is in early stages of development.
-    fn from_devel_status(status: &str) -> Self {
+    fn from_devel_status(status: &str, rules: &StatusRules) -> Self {
         match status.to_lowercase().as_str() {
-            "to do" | "new" | "assigned" | "modified" => Self::Warning("Early development.".into()),
+            "to do" | "new" | "assigned" | "modified" => {
+                Self::Warning("Early development.".into()).at_level(rules.checks.development)
+            }
             _ => Self::Ok,
         }
     }
 
     /// Report if the doc type is set to a non-release note type.
-    fn from_doc_type(doc_type: &str) -> Self {
-        match doc_type {
-            "If docs needed, set a value" => Self::Error("Bad doc type.".into()),
-            _ => Self::Ok,
+    fn from_doc_type(doc_type: &str, rules: &StatusRules) -> Self {
+        if is_listed(doc_type, &rules.placeholder_doc_types) {
+            Self::Error("Bad doc type.".into()).at_level(rules.checks.doc_type)
+        } else {
+            Self::Ok
         }
     }
 
@@ -363,6 +372,7 @@ impl Status {
         ticket_releases: &[String],
         likely_release: Option<Version>,
         doc_type: &str,
+        rules: &StatusRules,
     ) -> Self {
         if let Some(likely_release) = likely_release {
             // This is a replacement to the `contains` method that converts the `String` list to `&str`,
@@ -370,40 +380,55 @@ impl Status {
             if ticket_releases
                 .iter()
                 .any(|r| Version::from(r) == likely_release)
-                || UNCHECKED_DOC_TYPES.contains(&doc_type.to_lowercase().as_str())
+                || is_listed(doc_type, &rules.unchecked_doc_types)
             {
                 Self::Ok
             } else {
-                Self::Warning("Check target release.".into())
+                Self::Warning("Check target release.".into()).at_level(rules.checks.target_release)
             }
         } else {
             Self::Ok
         }
     }
+
+    /// Report if the release note isn't approved.
+    fn from_doc_text_status(doc_text_status: &DocTextStatus, rules: &StatusRules) -> Self {
+        Self::from(doc_text_status).at_level(rules.checks.doc_status)
+    }
 }
 
-impl From<DocTextStatus> for Status {
-    fn from(item: DocTextStatus) -> Self {
+/// Check if the value is in the configured list, regardless of letter case.
+fn is_listed(value: &str, list: &[String]) -> bool {
+    let value = value.to_lowercase();
+    list.iter().any(|item| item.to_lowercase() == value)
+}
+
+impl From<&DocTextStatus> for Status {
+    fn from(item: &DocTextStatus) -> Self {
         match item {
             DocTextStatus::Approved => Self::Ok,
             DocTextStatus::InProgress => Self::Error("RN not approved.".into()),
             DocTextStatus::NoDocumentation => Self::Error("RN not needed.".into()),
+            DocTextStatus::Unrecognized(value) => {
+                Self::Error(format!("Unknown RN status: {value}."))
+            }
         }
     }
 }
 
 impl AbstractTicket {
     /// Analyze the release note status of the ticket. Record the analysis as `Checks`.
-    fn checks(&self, release: Option<Version>) -> Checks {
+    fn checks(&self, release: Option<Version>, rules: &StatusRules) -> Checks {
         Checks {
-            development: Status::from_devel_status(&self.status),
-            title_and_text: Status::from_text(&self.doc_text),
-            doc_type: Status::from_doc_type(&self.doc_type),
-            doc_status: Status::from(self.doc_text_status),
+            development: Status::from_devel_status(&self.status, rules),
+            title_and_text: Status::from_text(&self.doc_text, rules),
+            doc_type: Status::from_doc_type(&self.doc_type, rules),
+            doc_status: Status::from_doc_text_status(&self.doc_text_status, rules),
             target_release: Status::from_target_release(
                 &self.target_releases,
                 release,
                 &self.doc_type,
+                rules,
             ),
         }
     }
@@ -633,9 +658,7 @@ fn most_common_release(tickets: &[AbstractTicket]) -> Option<Version> {
 }
 
 /// All the data that the status table needs to render.
-#[derive(Template, Serialize)] // this will generate the code...
-#[template(path = "status-table.html")] // using the template in this path, relative
-                                        // to the `templates` dir in the crate root
+#[derive(Serialize)]
 struct StatusTableTemplate<'a> {
     products: &'a str,
     release: &'a str,
@@ -645,11 +668,163 @@ struct StatusTableTemplate<'a> {
     generated_date: &'a str,
 }
 
+impl StatusTableTemplate<'_> {
+    /// Prepare the variables for the status table template. Jinja templates
+    /// can't call methods, so every displayed value is calculated in advance.
+    /// The built-in template uses the same variables as custom templates.
+    fn display_context(&self) -> StatusTableContext<'_> {
+        StatusTableContext {
+            products: self.products,
+            release: self.release,
+            overall_progress: (&self.overall_progress).into(),
+            tickets: self
+                .tickets_with_checks
+                .iter()
+                .map(|(ticket, checks)| StatusRow::new(ticket, checks))
+                .collect(),
+            per_writer_stats: self
+                .per_writer_stats
+                .iter()
+                .map(|stats| WriterRow {
+                    name: stats.name,
+                    total: stats.total,
+                    complete: stats.complete,
+                    warnings: stats.warnings,
+                    incomplete: stats.incomplete,
+                    percent: rounded(stats.percent()),
+                })
+                .collect(),
+            generated_date: self.generated_date,
+        }
+    }
+}
+
+/// The variables that the built-in or a custom status table template receives.
+#[derive(Template, Serialize)] // this will generate the code...
+#[template(path = "status-table.html")] // using the template in this path, relative
+                                        // to the `templates` dir in the crate root
+struct StatusTableContext<'a> {
+    products: &'a str,
+    release: &'a str,
+    overall_progress: DisplayedProgress,
+    tickets: Vec<StatusRow<'a>>,
+    per_writer_stats: Vec<WriterRow<'a>>,
+    generated_date: &'a str,
+}
+
+/// The overall progress, with the percentages rounded for display.
+#[derive(Serialize)]
+struct DisplayedProgress {
+    all: usize,
+    complete: usize,
+    complete_pct: u8,
+    warnings: usize,
+    warnings_pct: u8,
+    incomplete: usize,
+    incomplete_pct: u8,
+}
+
+impl From<&OverallProgress> for DisplayedProgress {
+    fn from(item: &OverallProgress) -> Self {
+        Self {
+            all: item.all,
+            complete: item.complete,
+            complete_pct: rounded(item.complete_pct),
+            warnings: item.warnings,
+            warnings_pct: rounded(item.warnings_pct),
+            incomplete: item.incomplete,
+            incomplete_pct: rounded(item.incomplete_pct),
+        }
+    }
+}
+
+/// Round a percentage to a whole number for display.
+// The percentage is always between 0 and 100, so the conversion can't lose any information.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn rounded(percentage: f32) -> u8 {
+    percentage.round() as u8
+}
+
+/// A ticket in the status table, with all its values prepared for display.
+#[derive(Serialize)]
+struct StatusRow<'a> {
+    id: String,
+    url: &'a str,
+    summary: &'a str,
+    doc_type: &'a str,
+    doc_text_status: String,
+    status: String,
+    docs_contact: &'a str,
+    assignee: &'a str,
+    target_releases: String,
+    priority: &'a str,
+    subsystems: String,
+    components: String,
+    flags_or_labels: String,
+    overall: StatusCell,
+    development: StatusCell,
+    title_and_text: StatusCell,
+}
+
+impl<'a> StatusRow<'a> {
+    fn new(ticket: &'a AbstractTicket, checks: &Checks) -> Self {
+        Self {
+            id: ticket.id.to_string(),
+            url: &ticket.url,
+            summary: &ticket.summary,
+            doc_type: &ticket.doc_type,
+            doc_text_status: ticket.doc_text_status.to_string(),
+            status: ticket.display_status(),
+            docs_contact: ticket.docs_contact_short(),
+            assignee: ticket.assignee_short(),
+            target_releases: ticket.display_target_releases(),
+            priority: &ticket.priority,
+            subsystems: ticket.display_subsystems(),
+            components: ticket.display_components(),
+            flags_or_labels: ticket.flags_or_labels(),
+            overall: (&checks.overall()).into(),
+            development: (&checks.development).into(),
+            title_and_text: (&checks.title_and_text).into(),
+        }
+    }
+}
+
+/// The result of a check, with its message and its color in the status table.
+#[derive(Serialize)]
+struct StatusCell {
+    message: String,
+    color: &'static str,
+}
+
+impl From<&Status> for StatusCell {
+    fn from(item: &Status) -> Self {
+        Self {
+            message: item.message().to_string(),
+            color: item.color(),
+        }
+    }
+}
+
+/// The statistics of a writer, including the calculated percentage of complete release notes.
+#[derive(Serialize)]
+struct WriterRow<'a> {
+    name: &'a str,
+    total: i32,
+    complete: i32,
+    warnings: i32,
+    incomplete: i32,
+    percent: u8,
+}
+
 /// Analyze all tickets and release notes, and produce a status table in two variants:
 ///
 /// * As text with HTML markup.
 /// * As a JSON map in text form.
-pub fn analyze_status(tickets: &[AbstractTicket]) -> Result<(String, String)> {
+pub fn analyze_status(
+    tickets: &[AbstractTicket],
+    rules: &StatusRules,
+    custom_templates: &CustomTemplates,
+) -> Result<(String, String)> {
     // Determine the product and release.
     let product = most_common_product(tickets);
     let release = most_common_release(tickets);
@@ -668,7 +843,7 @@ pub fn analyze_status(tickets: &[AbstractTicket]) -> Result<(String, String)> {
     // needs to receive both tickets and checks by reference.
     let checks: Vec<Checks> = tickets
         .iter()
-        .map(|ticket| ticket.checks(release))
+        .map(|ticket| ticket.checks(release, rules))
         .collect();
     let tickets_with_checks: Vec<(&AbstractTicket, &Checks)> =
         tickets.iter().zip(checks.iter()).collect();
@@ -686,8 +861,11 @@ pub fn analyze_status(tickets: &[AbstractTicket]) -> Result<(String, String)> {
         generated_date: &date_today,
     };
 
-    let as_html = status_table
-        .render()
+    let as_html = custom_templates
+        .render(
+            custom_templates::STATUS_TABLE,
+            &status_table.display_context(),
+        )
         .wrap_err("Failed to prepare the status table.")?;
 
     let as_json = serde_json::to_string(&status_table)
@@ -767,4 +945,108 @@ mod tests {
         assert!(version_8_9_1 > version_none);
         assert!(version_9 > version_8_9_1);
     }
+
+    #[test]
+    fn configurable_title_checks() {
+        let title = format!(".{}", "a".repeat(130));
+
+        let default_rules = StatusRules::default();
+        assert!(matches!(
+            Status::from_title(&title, &default_rules),
+            Status::Warning(_)
+        ));
+
+        let mut rules = StatusRules {
+            max_title_length: 150,
+            ..Default::default()
+        };
+        assert!(matches!(Status::from_title(&title, &rules), Status::Ok));
+
+        rules.checks.text = CheckLevel::Off;
+        assert!(matches!(
+            Status::from_title("No title here.", &rules),
+            Status::Ok
+        ));
+    }
+
+    #[test]
+    fn generated_titles() {
+        let mut ticket = crate::ticket_abstraction::tests::ticket("A-1");
+        ticket.summary = "[RFE] Faster boot ".to_string();
+        ticket.doc_text = "The system now boots faster.".to_string();
+
+        let rules = StatusRules::default();
+        assert!(matches!(
+            Status::from_text(&ticket.doc_text, &rules),
+            Status::Error(_)
+        ));
+
+        let cleanup = crate::config::Cleanup::try_from(r"^\[RFE\]".to_string())
+            .expect("Invalid cleanup in a test.");
+        ticket.add_title_from_summary(Some(&cleanup));
+        assert_eq!(
+            ticket.doc_text,
+            ".Faster boot\n\nThe system now boots faster."
+        );
+        assert!(matches!(
+            Status::from_text(&ticket.doc_text, &rules),
+            Status::Ok
+        ));
+
+        // A release note with a title keeps it.
+        ticket.add_title_from_summary(None);
+        assert_eq!(
+            ticket.doc_text,
+            ".Faster boot\n\nThe system now boots faster."
+        );
+
+        // A summary that the cleanup removes entirely adds no title.
+        ticket.doc_text = "The system now boots faster.".to_string();
+        ticket.summary = "[RFE]".to_string();
+        ticket.add_title_from_summary(Some(&cleanup));
+        assert_eq!(ticket.doc_text, "The system now boots faster.");
+
+        // A title can start with one dot of its own.
+        ticket.summary = ".NET support".to_string();
+        ticket.add_title_from_summary(None);
+        assert_eq!(
+            ticket.doc_text,
+            "..NET support\n\nThe system now boots faster."
+        );
+
+        // A summary that would form a block delimiter stays a title.
+        ticket.doc_text = "The system now boots faster.".to_string();
+        ticket.summary = "...".to_string();
+        ticket.add_title_from_summary(None);
+        assert_eq!(
+            ticket.doc_text,
+            ".{empty}...\n\nThe system now boots faster."
+        );
+    }
+
+    /// The built-in status table doubles as a starting point for custom templates,
+    /// so the Jinja engine must render it the same way as the built-in engine.
+    #[test]
+    fn render_builtin_status_table_as_custom() {
+        let mut incomplete = crate::ticket_abstraction::tests::ticket("A-2");
+        incomplete.doc_text = "No title <here>.".to_string();
+        incomplete.summary = "Fix the \"quoted\" & <tagged> summary".to_string();
+        let tickets = [crate::ticket_abstraction::tests::ticket("A-1"), incomplete];
+        let rules = StatusRules::default();
+
+        let builtin_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("templates");
+        let as_custom =
+            CustomTemplates::load(&builtin_dir).expect("Failed to load the built-in templates.");
+
+        let (builtin_html, builtin_json) =
+            analyze_status(&tickets, &rules, &CustomTemplates::default())
+                .expect("Failed to render the built-in status table.");
+        let (custom_html, custom_json) = analyze_status(&tickets, &rules, &as_custom)
+            .expect("Failed to render the status table as a custom template.");
+
+        // The Jinja engine also escapes slashes, which doesn't change the displayed page.
+        assert_eq!(builtin_html, custom_html.replace("&#x2f;", "/"));
+        assert_eq!(builtin_json, custom_json);
+        assert!(builtin_html.contains("&lt;tagged&gt;"));
+    }
 }
@@ -21,56 +21,70 @@ use std::fmt;
 
 use askama::Template;
 use color_eyre::{eyre::Context, Result};
+use serde::{Serialize, Serializer};
 
+use crate::config;
+use crate::custom_templates::{self, CustomTemplates};
 use crate::extra_fields::DocTextStatus;
 use crate::templating::DocumentVariant;
 use crate::AbstractTicket;
 
-// TODO: We might want these to be configurable.
-/// Documentation components that only categorize tickets internally.
-const THROWAWAY_COMPONENTS: [&str; 3] = ["releng", "(none)", "Documentation"];
-/// Prefixes shared by other internal, documentation components.
-const THROWAWAY_PREFIXES: [&str; 2] = ["doc-", "Red_Hat_Enterprise_Linux-Release_Notes"];
-/// The placeholder that renames the internal, documentation components.
-const COMPONENT_PLACEHOLDER: &str = "other";
-
 /// A list of all the ticket signatures that belong under this component.
-#[derive(Eq, PartialEq, PartialOrd, Ord)]
+#[derive(Eq, PartialEq, PartialOrd, Ord, Serialize)]
 struct TicketsByComponent<'a> {
     component: PresentableComponent<'a>,
     signatures: Vec<String>,
 }
 
 /// A representation of the AsciiDoc template for the appendix. Later rendered.
-#[derive(Template)]
+#[derive(Template, Serialize)]
 #[template(path = "summary-list.adoc", escape = "none")]
 struct SummaryList<'a> {
     tickets_by_components: &'a [TicketsByComponent<'a>],
 }
 
 /// A wrapper around tickets components. It keeps all internal components separate
-/// in the `Internal` variant. External components are unchanged in the `External` variant.
+/// in the `Internal` variant, which carries the configured placeholder.
+/// External components are displayed under their configured alias, if any, in the `External` variant.
 #[derive(Eq, Hash, PartialEq, PartialOrd, Ord)]
 enum PresentableComponent<'a> {
     External(&'a str),
-    Internal,
+    Internal(&'a str),
 }
 
 impl<'a> PresentableComponent<'a> {
     /// Store the component either as external or as internal.
-    fn from(component: &'a str) -> Self {
-        if THROWAWAY_COMPONENTS.contains(&component)
-            || THROWAWAY_PREFIXES
+    fn from(component: &'a str, settings: &'a config::Appendix) -> Self {
+        // Replace the component with its display name first, so that the alias
+        // can also fold the component away as internal.
+        let component = settings
+            .aliases
+            .get(component)
+            .map_or(component, String::as_str);
+
+        if settings.internal_components.iter().any(|c| c == component)
+            || settings
+                .internal_prefixes
                 .iter()
-                .any(|prefix| component.starts_with(prefix))
+                .any(|prefix| component.starts_with(prefix.as_str()))
         {
-            Self::Internal
+            Self::Internal(&settings.internal_placeholder)
         } else {
             Self::External(component)
         }
     }
 }
 
+// Templates receive the component in its displayed form.
+impl Serialize for PresentableComponent<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
 impl fmt::Display for PresentableComponent<'_> {
     /// Display the component. Adds backticks for AsciiDoc formatting.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -78,7 +92,7 @@ impl fmt::Display for PresentableComponent<'_> {
             // If the variant is an actual component, format it with backticks as a code literal.
             PresentableComponent::External(component) => write!(f, "`{component}`"),
             // If the variant is a throwaway component, replace it with an unformatted placeholder.
-            PresentableComponent::Internal => write!(f, "{COMPONENT_PLACEHOLDER}"),
+            PresentableComponent::Internal(placeholder) => write!(f, "{placeholder}"),
         }
     }
 }
@@ -87,6 +101,7 @@ impl fmt::Display for PresentableComponent<'_> {
 fn groups<'a>(
     tickets: &[&'a AbstractTicket],
     variant: DocumentVariant,
+    settings: &'a config::Appendix,
 ) -> Vec<TicketsByComponent<'a>> {
     // Use an intermediate `HashMap` for grouping.
     let mut components: HashMap<PresentableComponent, Vec<String>> = HashMap::new();
@@ -98,12 +113,15 @@ fn groups<'a>(
         .filter(|ticket| filter_doc_text(ticket, variant))
         .for_each(|ticket| {
             for component in &ticket.components {
-                let presentable = PresentableComponent::from(component);
-
-                components
-                    .entry(presentable)
-                    .and_modify(|c| c.push(ticket.xref()))
-                    .or_insert_with(|| vec![ticket.xref()]);
+                let presentable = PresentableComponent::from(component, settings);
+                let signatures = components.entry(presentable).or_default();
+
+                // Several components of the ticket might share the same heading.
+                // List the ticket only once under it.
+                let xref = ticket.xref();
+                if !signatures.contains(&xref) {
+                    signatures.push(xref);
+                }
             }
         });
 
@@ -121,18 +139,24 @@ fn groups<'a>(
 ///
 /// * In the external variant, only list tickets with an approved doc text.
 /// * In the internal variant, list all tickets.
+/// * In a custom variant, list all tickets that the variant includes.
 fn filter_doc_text(ticket: &AbstractTicket, variant: DocumentVariant) -> bool {
     match variant {
-        DocumentVariant::Internal => true,
+        DocumentVariant::Internal | DocumentVariant::Custom { .. } => true,
         DocumentVariant::External => ticket.doc_text_status == DocTextStatus::Approved,
     }
 }
 
 /// Produce an AsciiDoc appendix file that lists all tickets in the document
 /// by their component in a sorted table.
-pub fn appendix(tickets: &[&AbstractTicket], variant: DocumentVariant) -> Result<String> {
+pub fn appendix(
+    tickets: &[&AbstractTicket],
+    variant: DocumentVariant,
+    settings: &config::Appendix,
+    custom_templates: &CustomTemplates,
+) -> Result<String> {
     // Prepare ticket signatures grouped by component.
-    let mut groups = groups(tickets, variant);
+    let mut groups = groups(tickets, variant, settings);
 
     // Sort the list by component name, alphabetically.
     // The 'other' group ends up at the very end, because it's a separate `enum` variant.
@@ -144,7 +168,64 @@ pub fn appendix(tickets: &[&AbstractTicket], variant: DocumentVariant) -> Result
     };
 
     // Render the template as a valid AsciiDoc string.
-    template
-        .render()
+    custom_templates
+        .render(custom_templates::SUMMARY_LIST, &template)
         .wrap_err("Failed to prepare the ticket appendix.")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ticket_abstraction::tests::ticket;
+
+    #[test]
+    fn group_components_by_alias() {
+        let mut settings = config::Appendix::default();
+        settings
+            .aliases
+            .insert("kernel-rt".to_string(), "kernel".to_string());
+        settings
+            .aliases
+            .insert("secret-tool".to_string(), "releng".to_string());
+
+        let mut rt = ticket("A-1");
+        rt.components = vec!["kernel".to_string(), "kernel-rt".to_string()];
+        let mut internal = ticket("A-2");
+        internal.components = vec!["secret-tool".to_string()];
+        let tickets = [&rt, &internal];
+
+        let mut groups = groups(&tickets, DocumentVariant::Internal, &settings);
+        groups.sort_unstable();
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].component.to_string(), "`kernel`");
+        // The ticket appears only once even though both its components share the heading.
+        assert_eq!(groups[0].signatures, [rt.xref()]);
+        assert_eq!(groups[1].component.to_string(), "other");
+    }
+
+    #[test]
+    fn render_builtin_appendix_as_custom() {
+        let mut second = ticket("A-2");
+        second.components = vec!["kernel".to_string(), "systemd".to_string()];
+        let tickets = [&ticket("A-1"), &second];
+        let settings = config::Appendix::default();
+
+        let builtin_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("templates");
+        let as_custom =
+            CustomTemplates::load(&builtin_dir).expect("Failed to load the built-in templates.");
+
+        let builtin = appendix(
+            &tickets,
+            DocumentVariant::Internal,
+            &settings,
+            &CustomTemplates::default(),
+        )
+        .expect("Failed to render the built-in appendix.");
+        let custom = appendix(&tickets, DocumentVariant::Internal, &settings, &as_custom)
+            .expect("Failed to render the appendix as a custom template.");
+
+        assert_eq!(builtin, custom);
+        assert!(builtin.contains(&format!("{}, {}", ticket("A-1").xref(), second.xref())));
+    }
+}
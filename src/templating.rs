@@ -16,18 +16,25 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::rc::Rc;
 
 use askama::Template;
-//use color_eyre::Result;
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
 
 use crate::config;
+use crate::custom_templates::{self, CustomTemplates};
+use crate::extra_fields::DocTextStatus;
 use crate::ticket_abstraction::AbstractTicket;
 use crate::ticket_abstraction::TicketId;
+use crate::translation::{Message, Translations};
 
 /// A leaf, reference module that contains release notes with no further nesting.
-#[derive(Template)]
+#[derive(Template, Serialize)]
 #[template(path = "reference.adoc", escape = "none")]
 struct Leaf<'a> {
     id: &'a str,
@@ -37,7 +44,7 @@ struct Leaf<'a> {
 }
 
 /// An assembly module that nests other assemblies or leaf reference modules.
-#[derive(Template)]
+#[derive(Template, Serialize)]
 #[template(path = "assembly.adoc", escape = "none")]
 struct Assembly<'a> {
     id: &'a str,
@@ -46,14 +53,84 @@ struct Assembly<'a> {
     includes: &'a [String],
 }
 
+/// The structure of a generated module and the release notes in it,
+/// for the machine-readable export of the document.
+#[derive(Clone, Debug, Serialize)]
+pub struct Outline {
+    id: String,
+    title: String,
+    file_name: String,
+    intro_abstract: Option<String>,
+    /// The release notes in a leaf module.
+    release_notes: Vec<OutlineNote>,
+    /// The sections that an assembly includes.
+    sections: Vec<Outline>,
+}
+
+/// One copy of a release note in the document.
+#[derive(Clone, Debug)]
+pub struct OutlineNote {
+    /// The anchor of this copy of the release note.
+    anchor: String,
+    /// The signature that links to the ticket.
+    signature: String,
+    /// The release note, formatted as AsciiDoc.
+    text: String,
+    ticket: AbstractTicket,
+    /// Whether the document variant shows the debugging information, and with it,
+    /// all fields of the ticket.
+    debug_info: bool,
+}
+
+// The fields of the ticket depend on the document variant, so the serialization is manual.
+impl Serialize for OutlineNote {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("OutlineNote", 4)?;
+        state.serialize_field("anchor", &self.anchor)?;
+        state.serialize_field("signature", &self.signature)?;
+        state.serialize_field("text", &self.text)?;
+        if self.debug_info {
+            state.serialize_field("ticket", &self.ticket)?;
+        } else {
+            state.serialize_field("ticket", &self.ticket.public_fields())?;
+        }
+        state.end()
+    }
+}
+
+/// The file names of generated modules, unless the templates configure a different pattern.
+const DEFAULT_FILE_NAME_PATTERN: &str = "{type}_{id}.adoc";
+
+/// The ID of the appendix with release notes that no section includes.
+const UNPLACED_ID: &str = "unplaced-release-notes";
+
+/// The block attribute that marks a module as an appendix.
+const APPENDIX_ATTRIBUTE: &str = "[appendix]";
+
 /// The variant of the generated, output document:
 ///
 /// * `External`: The external variant intended for publishing the release notes.
 /// * `Internal`: The debugging variant intended for preparing the release notes.
+/// * `Custom`: A variant that the project defines, optionally with the debugging information.
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum DocumentVariant {
     External,
     Internal,
+    Custom { debug_info: bool },
+}
+
+impl DocumentVariant {
+    /// Returns `true` if the release notes in this variant show the debugging information line.
+    pub fn debug_info(self) -> bool {
+        match self {
+            Self::Internal => true,
+            Self::External => false,
+            Self::Custom { debug_info } => debug_info,
+        }
+    }
 }
 
 /// The representation of a module, before being finally rendered.
@@ -83,6 +160,13 @@ impl Module {
             Self::Blank { file_name, .. } | Self::WithContent { file_name, .. } => file_name,
         }
     }
+    /// Return `true` if the module is an appendix rather than a chapter.
+    pub fn is_appendix(&self) -> bool {
+        match self {
+            Self::WithContent { text, .. } => text.starts_with(APPENDIX_ATTRIBUTE),
+            Self::Blank { .. } => false,
+        }
+    }
     /// Return `true` if the module is of the `WithContent` variant.
     fn has_content(&self) -> bool {
         match self {
@@ -92,6 +176,36 @@ impl Module {
     }
 }
 
+/// The shared state while formatting the modules of a document variant.
+/// Every section passes it down to its subsections.
+struct Context<'a> {
+    variant: DocumentVariant,
+    with_priv_footnote: bool,
+    placement: config::Placement,
+    file_name_pattern: &'a str,
+    /// Output templates that the project overrides.
+    custom_templates: &'a CustomTemplates,
+    /// Tickets that no regular section includes. Catch-all sections collect these.
+    unplaced: HashSet<Rc<TicketId>>,
+    /// The number of leaf modules that include each ticket.
+    ticket_stats: HashMap<Rc<TicketId>, u32>,
+    /// The file names of the sections in the templates, and of the sections generated so far.
+    file_names: HashSet<String>,
+}
+
+impl OutlineNote {
+    /// Describe one copy of the release note of this ticket.
+    fn new(ticket: &AbstractTicket, repeated_in: Option<&str>, context: &Context<'_>) -> Self {
+        Self {
+            anchor: ticket.copy_anchor(repeated_in),
+            signature: ticket.signature(context.with_priv_footnote),
+            text: ticket.release_note(context.variant, context.with_priv_footnote, repeated_in),
+            ticket: ticket.clone(),
+            debug_info: context.variant.debug_info(),
+        }
+    }
+}
+
 /// Convert a section title to an ID that's sanitized for AsciiDoc and HTML.
 ///
 /// This function is taken from `newdoc` (<https://github.com/redhat-documentation/newdoc>).
@@ -176,32 +290,53 @@ fn id_fragment(title: &str) -> String {
 
 impl config::Section {
     /// Convert the body of the section into AsciiDoc text that will serve
-    /// as the body of the resulting module.
+    /// as the body of the resulting module. Also returns the release notes in the module.
     fn render(
         &self,
         id: &str,
         tickets: &[&AbstractTicket],
-        variant: DocumentVariant,
-        with_priv_footnote: bool,
-        ticket_stats: &mut HashMap<Rc<TicketId>, u32>,
-    ) -> Option<String> {
-        let matching_tickets: Vec<_> = tickets.iter().filter(|t| self.matches_ticket(t)).collect();
-
-        // Record usage statistics for this leaf module
-        for ticket in &matching_tickets {
-            ticket_stats
-                .entry(Rc::clone(&ticket.id))
-                .and_modify(|counter| *counter += 1)
-                .or_insert(1);
-        }
+        context: &mut Context<'_>,
+    ) -> Result<Option<(String, Vec<OutlineNote>)>> {
+        let matching_tickets: Vec<_> = tickets
+            .iter()
+            .filter(|t| self.matches_ticket(t))
+            // In the first-match placement, skip tickets that an earlier module already used.
+            .filter(|t| {
+                context.placement == config::Placement::All
+                    || context
+                        .ticket_stats
+                        .get(&t.id)
+                        .map_or(true, |count| *count == 0)
+            })
+            .collect();
 
         if matching_tickets.is_empty() {
-            None
+            Ok(None)
         } else {
-            let release_notes: Vec<_> = matching_tickets
+            let mut outline_notes: Vec<_> = matching_tickets
                 .iter()
-                .map(|t| t.release_note(variant, with_priv_footnote))
+                .map(|t| {
+                    // Record usage statistics for this leaf module
+                    let occurrence = context
+                        .ticket_stats
+                        .entry(Rc::clone(&t.id))
+                        .and_modify(|counter| *counter += 1)
+                        .or_insert(1);
+
+                    // Only the first copy of the release note uses the primary anchor.
+                    let repeated_in = (*occurrence > 1).then_some(id);
+
+                    OutlineNote::new(t, repeated_in, context)
+                })
                 .collect();
+            let release_notes: Vec<String> = if let Some(field) = self.subheadings {
+                let (sorted_notes, release_notes) =
+                    with_subheadings(field, id, self.other_title(), outline_notes);
+                outline_notes = sorted_notes;
+                release_notes
+            } else {
+                outline_notes.iter().map(|note| note.text.clone()).collect()
+            };
 
             let template = Leaf {
                 id,
@@ -211,58 +346,59 @@ impl config::Section {
                 release_notes: &release_notes,
             };
 
-            Some(
-                template
-                    .render()
-                    .expect("Failed to render a reference module template."),
-            )
+            let text = context
+                .custom_templates
+                .render(custom_templates::REFERENCE, &template)?;
+
+            Ok(Some((text, outline_notes)))
         }
     }
 
     /// Convert the section into either a leaf module, or into an assembly and all
-    /// the modules that it includes, recursively.
+    /// the modules that it includes, recursively. Also returns the outline of the modules.
     ///
-    /// Returns `Blank` if the module or assembly captured no release notes at all.
+    /// Returns `Blank`, with no outline, if the module or assembly captured
+    /// no release notes at all.
     fn modules(
         &self,
         tickets: &[&AbstractTicket],
         prefix: Option<&str>,
-        variant: DocumentVariant,
-        with_priv_footnote: bool,
-        ticket_stats: &mut HashMap<Rc<TicketId>, u32>,
-    ) -> Module {
+        context: &mut Context<'_>,
+    ) -> Result<(Module, Option<Outline>)> {
+        // A grouping section turns into an assembly with a subsection for each value.
+        if let Some(field) = self.group_by {
+            return self
+                .expanded(field, tickets, prefix, context)?
+                .modules(tickets, prefix, context);
+        }
+
         let matching_tickets: Vec<&AbstractTicket> = tickets
             .iter()
             .filter(|&&t| self.matches_ticket(t))
+            // A catch-all section only receives tickets that no regular section includes.
+            .filter(|t| !self.is_catch_all() || context.unplaced.contains(&t.id))
             .copied()
             .collect();
+        let matching_tickets = self.sorted(matching_tickets);
 
-        let module_id_fragment = id_fragment(&self.title);
-        let module_id = if let Some(prefix) = prefix {
-            format!("{prefix}-{module_id_fragment}")
-        } else {
-            module_id_fragment
-        };
+        let module_id = self.module_id(prefix);
+        let file_name = self.file_name(&module_id, context.file_name_pattern);
 
         // If the section includes other sections, treat it as an assembly.
         if let Some(sections) = &self.subsections {
-            let file_name = format!("assembly_{module_id}.adoc");
-            let included_modules: Vec<Module> = sections
-                .iter()
-                .map(|s| {
-                    s.modules(
-                        &matching_tickets,
-                        Some(&module_id),
-                        variant,
-                        with_priv_footnote,
-                        ticket_stats,
-                    )
-                })
-                .filter(Module::has_content)
-                .collect();
+            let mut included_modules: Vec<Module> = Vec::new();
+            let mut outlines: Vec<Outline> = Vec::new();
+            for section in sections {
+                let (module, outline) =
+                    section.modules(&matching_tickets, Some(&module_id), context)?;
+                if module.has_content() {
+                    included_modules.push(module);
+                }
+                outlines.extend(outline);
+            }
             // If the assembly receives no modules, because all its modules are empty, return Blank.
             if included_modules.is_empty() {
-                Module::Blank { file_name }
+                Ok((Module::Blank { file_name }, None))
             } else {
                 let include_statements: Vec<String> = included_modules
                     .iter()
@@ -277,52 +413,466 @@ impl config::Section {
                     includes: &include_statements,
                 };
 
-                let text = template
-                    .render()
-                    .expect("Failed to render an assembly template.");
+                let text = context
+                    .custom_templates
+                    .render(custom_templates::ASSEMBLY, &template)?;
 
-                Module::WithContent {
-                    file_name,
-                    text,
-                    included_modules: Some(included_modules),
-                }
+                let outline = self.outline(module_id, file_name.clone(), Vec::new(), outlines);
+
+                Ok((
+                    Module::WithContent {
+                        file_name,
+                        text,
+                        included_modules: Some(included_modules),
+                    },
+                    Some(outline),
+                ))
             }
         // If the section includes no sections, treat it as a leaf, reference module.
         } else {
             // If the module receives no release notes and its body is empty, return Blank.
             // Otherwise, return the module formatted with its release notes.
-            let text = self.render(
-                &module_id,
-                tickets,
-                variant,
-                with_priv_footnote,
-                ticket_stats,
-            );
-            let file_name = format!("ref_{module_id}.adoc");
-            if let Some(text) = text {
-                Module::WithContent {
-                    file_name,
-                    text,
-                    included_modules: None,
-                }
+            let rendered = self.render(&module_id, &matching_tickets, context)?;
+            if let Some((text, notes)) = rendered {
+                let outline = self.outline(module_id, file_name.clone(), notes, Vec::new());
+
+                Ok((
+                    Module::WithContent {
+                        file_name,
+                        text,
+                        included_modules: None,
+                    },
+                    Some(outline),
+                ))
             } else {
-                Module::Blank { file_name }
+                Ok((Module::Blank { file_name }, None))
+            }
+        }
+    }
+
+    /// Copy the grouping section, with a generated subsection for each distinct value
+    /// of the field among the matching tickets, in alphabetical order. The last subsection
+    /// collects the tickets with no value.
+    ///
+    /// The ID of each subsection is based on the value, so that it stays the same
+    /// regardless of the title pattern. Fails if a subsection would overwrite
+    /// the file of another section.
+    fn expanded(
+        &self,
+        field: config::TicketField,
+        tickets: &[&AbstractTicket],
+        prefix: Option<&str>,
+        context: &mut Context<'_>,
+    ) -> Result<Self> {
+        let module_id = self.module_id(prefix);
+        let title_pattern = self.group_title.as_deref().unwrap_or("{value}");
+
+        // Values that differ only in letter case share a subsection.
+        let mut values: BTreeMap<String, &str> = BTreeMap::new();
+        let mut without_value = false;
+        for ticket in tickets
+            .iter()
+            .filter(|t| self.matches_ticket(t))
+            .filter(|t| !self.is_catch_all() || context.unplaced.contains(&t.id))
+        {
+            let ticket_values = field.values(ticket);
+            without_value |= ticket_values.is_empty();
+            for value in ticket_values {
+                values.entry(value.to_lowercase()).or_insert(value);
+            }
+        }
+        let values: Vec<&str> = values.into_values().collect();
+
+        let mut ids = UniqueIds::default();
+        // Reserve the ID of the subsection with no value first, so that no value can take it.
+        let other_id = ids.unique(&format!("{module_id}-{}", field.no_value_id()));
+
+        let subsection = |value: &str, id: String, filter: config::Filter| Self {
+            title: title_pattern.replace("{value}", value),
+            id: Some(id),
+            filter,
+            // The subsections inherit the subheadings, and the order from the grouping section.
+            subheadings: self.subheadings,
+            other_title: self.other_title.clone(),
+            ..Self::default()
+        };
+
+        let mut subsections: Vec<Self> = values
+            .iter()
+            .map(|value| {
+                let id = ids.unique(&format!("{module_id}-{}", id_fragment(value)));
+                subsection(value, id, field.filter(&[value]))
+            })
+            .collect();
+
+        if without_value {
+            let filter = config::Filter {
+                not: Some(Box::new(field.filter(&values))),
+                ..Default::default()
+            };
+            subsections.push(subsection(self.other_title(), other_id, filter));
+        }
+
+        for section in &subsections {
+            let file_name = section.file_name(
+                &section.module_id(Some(&module_id)),
+                context.file_name_pattern,
+            );
+            if !context.file_names.insert(file_name.clone()) {
+                bail!(
+                    "The generated section `{}` would overwrite the file of another section: {}",
+                    section.title,
+                    file_name
+                );
+            }
+        }
+
+        Ok(Self {
+            group_by: None,
+            subsections: Some(subsections),
+            ..self.clone()
+        })
+    }
+
+    /// Describe the generated module of this section in the outline of the document.
+    fn outline(
+        &self,
+        id: String,
+        file_name: String,
+        release_notes: Vec<OutlineNote>,
+        sections: Vec<Outline>,
+    ) -> Outline {
+        Outline {
+            id,
+            title: self.title.clone(),
+            file_name,
+            intro_abstract: self.intro_abstract.clone(),
+            release_notes,
+            sections,
+        }
+    }
+
+    /// The ID of the module. Unless the section sets its own ID, the ID is based on the title,
+    /// and prefixed with the ID of the parent section.
+    fn module_id(&self, prefix: Option<&str>) -> String {
+        if let Some(id) = &self.id {
+            return id.clone();
+        }
+
+        let module_id_fragment = id_fragment(&self.title);
+        if let Some(prefix) = prefix {
+            format!("{prefix}-{module_id_fragment}")
+        } else {
+            module_id_fragment
+        }
+    }
+
+    /// Whether the section only collects the tickets that no other section includes.
+    fn is_catch_all(&self) -> bool {
+        self.catch_all.unwrap_or(false)
+    }
+
+    /// The value that the subheading or the generated subsection with no value displays.
+    fn other_title(&self) -> &str {
+        self.other_title.as_deref().unwrap_or(OTHER_TITLE)
+    }
+
+    /// The file name of the module. Unless the section sets its own file name,
+    /// the file name follows the pattern, where `{type}` is either `assembly` or `ref`.
+    fn file_name(&self, module_id: &str, pattern: &str) -> String {
+        if let Some(file_name) = &self.file_name {
+            return file_name.clone();
+        }
+
+        let module_type = if self.subsections.is_some() || self.group_by.is_some() {
+            "assembly"
+        } else {
+            "ref"
+        };
+
+        file_name_from_pattern(pattern, module_type, module_id)
+    }
+
+    /// Record the file names of this section and of its subsections, recursively.
+    fn record_file_names(&self, prefix: Option<&str>, pattern: &str, file_names: &mut Vec<String>) {
+        let module_id = self.module_id(prefix);
+        file_names.push(self.file_name(&module_id, pattern));
+
+        if let Some(sections) = &self.subsections {
+            for section in sections {
+                section.record_file_names(Some(&module_id), pattern, file_names);
             }
         }
     }
 
+    /// Record the translatable texts of this section and of its subsections, recursively.
+    fn translatable_texts(&self, prefix: Option<&str>, messages: &mut Vec<Message>) {
+        let module_id = self.module_id(prefix);
+        messages.push(Message::section(&module_id, "title", &self.title));
+        if let Some(group_title) = &self.group_title {
+            messages.push(Message::section(&module_id, "group_title", group_title));
+        }
+        if self.subheadings.is_some() || self.group_by.is_some() {
+            messages.push(Message::section(
+                &module_id,
+                "other_title",
+                self.other_title(),
+            ));
+        }
+        if let Some(intro_abstract) = &self.intro_abstract {
+            messages.push(Message::section(
+                &module_id,
+                "intro_abstract",
+                intro_abstract,
+            ));
+        }
+
+        if let Some(sections) = &self.subsections {
+            for section in sections {
+                section.translatable_texts(Some(&module_id), messages);
+            }
+        }
+    }
+
+    /// Replace the title and the abstract of this section and of its subsections
+    /// with their translations, recursively. Each section keeps its English ID.
+    fn localize(&mut self, prefix: Option<&str>, translations: &Translations) {
+        let module_id = self.module_id(prefix);
+
+        let title = Message::section(&module_id, "title", &self.title);
+        if let Some(text) = translations.get(&title.context, &title.source) {
+            self.title = text.to_string();
+        }
+
+        let intro_abstract = self.intro_abstract.as_ref().and_then(|intro_abstract| {
+            let message = Message::section(&module_id, "intro_abstract", intro_abstract);
+            translations.get(&message.context, &message.source)
+        });
+        if let Some(text) = intro_abstract {
+            self.intro_abstract = Some(text.to_string());
+        }
+
+        let group_title = self.group_title.as_ref().and_then(|group_title| {
+            let message = Message::section(&module_id, "group_title", group_title);
+            translations.get(&message.context, &message.source)
+        });
+        if let Some(text) = group_title {
+            self.group_title = Some(text.to_string());
+        }
+
+        // Sections that use the default title can translate it, too.
+        let other_title = Message::section(&module_id, "other_title", self.other_title());
+        if let Some(text) = translations.get(&other_title.context, &other_title.source) {
+            self.other_title = Some(text.to_string());
+        }
+
+        if let Some(sections) = &mut self.subsections {
+            for section in sections {
+                section.localize(Some(&module_id), translations);
+            }
+        }
+
+        // The translated title would otherwise produce a different ID and file name.
+        self.id = Some(module_id);
+    }
+
     /// Checks whether this section, with its filter configuration, can include a particular ticket.
     fn matches_ticket(&self, ticket: &AbstractTicket) -> bool {
-        let matches_doc_type = match &self.filter.doc_type {
-            Some(doc_types) => doc_types
-                .iter()
-                // Compare both doc types in lower case
-                // TODO: Turn the `expect` into proper error handling. See also the other variables below.
-                .any(|dt| dt.to_lowercase() == ticket.doc_type.to_lowercase()),
-            // If the filter doesn't configure a doc type, match by default
-            None => true,
+        self.filter.matches(ticket)
+    }
+
+    /// Order the tickets by the configured sort keys. Subsections inherit the order,
+    /// unless they configure their own. The sort is stable, so without sort keys,
+    /// or for tickets that are equal in all keys, the original order remains.
+    fn sorted<'a>(&self, mut tickets: Vec<&'a AbstractTicket>) -> Vec<&'a AbstractTicket> {
+        if let Some(keys) = &self.sort_by {
+            tickets.sort_by(|a, b| {
+                keys.iter()
+                    .map(|key| key.compare(a, b))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+
+        tickets
+    }
+
+    /// Record the tickets that the leaf modules of this section include, recursively.
+    /// Skip catch-all sections, because they only collect the remaining tickets.
+    fn record_placed(&self, tickets: &[&AbstractTicket], placed: &mut HashSet<Rc<TicketId>>) {
+        if self.is_catch_all() {
+            return;
+        }
+
+        let matching_tickets: Vec<&AbstractTicket> = tickets
+            .iter()
+            .filter(|&&t| self.matches_ticket(t))
+            .copied()
+            .collect();
+
+        if let Some(sections) = &self.subsections {
+            for section in sections {
+                section.record_placed(&matching_tickets, placed);
+            }
+        } else {
+            for ticket in matching_tickets {
+                placed.insert(Rc::clone(&ticket.id));
+            }
+        }
+    }
+}
+
+/// The default title of the subheading with the release notes that have no value in the field.
+const OTHER_TITLE: &str = "Other";
+
+/// Arrange the release notes under a discrete subheading for each value of the field,
+/// in alphabetical order regardless of case. Release notes with no value come last.
+///
+/// Returns the release notes in their new order, and the texts of the subheadings
+/// and the release notes, ready for the leaf module.
+fn with_subheadings(
+    field: config::TicketField,
+    id: &str,
+    other_title: &str,
+    notes: Vec<OutlineNote>,
+) -> (Vec<OutlineNote>, Vec<String>) {
+    // Values that differ only in case share a subheading, which displays the first spelling.
+    let mut groups: BTreeMap<String, (String, Vec<OutlineNote>)> = BTreeMap::new();
+    let mut without_value = Vec::new();
+    for note in notes {
+        match field.first_value(&note.ticket) {
+            Some(value) => groups
+                .entry(value.to_lowercase())
+                .or_insert_with(|| (value.to_string(), Vec::new()))
+                .1
+                .push(note),
+            None => without_value.push(note),
+        }
+    }
+
+    let mut ids = UniqueIds::default();
+    // Reserve the ID of the subheading with no value first, so that no value can take it.
+    let other_id = ids.unique(&format!("{id}_{}", field.no_value_id()));
+
+    let mut sorted_notes = Vec::new();
+    let mut texts = Vec::new();
+    let mut add_group = |subheading_id: &str, subheading: &str, group: Vec<OutlineNote>| {
+        texts.push(format!(
+            "[discrete]\n[id=\"{subheading_id}\"]\n== {subheading}"
+        ));
+        texts.extend(group.iter().map(|note| note.text.clone()));
+        sorted_notes.extend(group);
+    };
+
+    for (subheading, group) in groups.into_values() {
+        let subheading_id = ids.unique(&format!("{id}_{}", id_fragment(&subheading)));
+        add_group(&subheading_id, &subheading, group);
+    }
+    if !without_value.is_empty() {
+        add_group(&other_id, other_title, without_value);
+    }
+
+    (sorted_notes, texts)
+}
+
+/// Generated IDs that are already in use. Different values can lead to the same ID,
+/// such as `foo bar` and `foo-bar`, so a repeated ID gets a number, such as `foo-bar-2`.
+#[derive(Default)]
+struct UniqueIds(HashSet<String>);
+
+impl UniqueIds {
+    /// Return the ID, or a numbered variant of it if the ID is already in use.
+    fn unique(&mut self, id: &str) -> String {
+        let mut unique = id.to_string();
+        let mut number = 1;
+        while self.0.contains(&unique) {
+            number += 1;
+            unique = format!("{id}-{number}");
+        }
+        self.0.insert(unique.clone());
+
+        unique
+    }
+}
+
+impl config::TicketField {
+    /// The ID fragment of a subheading or a subsection with no value in this field,
+    /// such as `no-component`.
+    fn no_value_id(self) -> &'static str {
+        match self {
+            Self::Component => "no-component",
+            Self::Subsystem => "no-subsystem",
+            Self::Product => "no-product",
+            Self::DocType => "no-doc-type",
+        }
+    }
+
+    /// All values of this field in the ticket. Empty values don't count.
+    fn values(self, ticket: &AbstractTicket) -> Vec<&str> {
+        let values = match self {
+            Self::Component => as_strs(&ticket.components),
+            // A ticket whose subsystems failed to load counts as having none.
+            Self::Subsystem => ticket
+                .subsystems
+                .as_ref()
+                .map_or_else(|_| Vec::new(), |ssts| as_strs(ssts)),
+            Self::Product => vec![ticket.product.as_str()],
+            Self::DocType => vec![ticket.doc_type.as_str()],
         };
-        let matches_subsystem = match &self.filter.subsystem {
+
+        values
+            .into_iter()
+            .filter(|value| !value.is_empty())
+            .collect()
+    }
+
+    /// The first value of this field in the ticket, if any.
+    fn first_value(self, ticket: &AbstractTicket) -> Option<&str> {
+        self.values(ticket).first().copied()
+    }
+
+    /// A filter that matches tickets with any of these values in this field.
+    fn filter(self, values: &[&str]) -> config::Filter {
+        let patterns = Some(
+            values
+                .iter()
+                .map(|value| config::Pattern::exact(value))
+                .collect(),
+        );
+
+        match self {
+            Self::Component => config::Filter {
+                component: patterns,
+                ..Default::default()
+            },
+            Self::Subsystem => config::Filter {
+                subsystem: patterns,
+                ..Default::default()
+            },
+            Self::Product => config::Filter {
+                product: patterns,
+                ..Default::default()
+            },
+            Self::DocType => config::Filter {
+                doc_type: patterns,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl config::Variant {
+    /// Checks whether this variant, with its filter configuration, includes a particular ticket.
+    pub fn includes(&self, ticket: &AbstractTicket) -> bool {
+        (self.include_unapproved || ticket.doc_text_status == DocTextStatus::Approved)
+            && self.filter.matches(ticket)
+    }
+}
+
+impl config::Filter {
+    /// Evaluates the filter, including all its nested filters, on a particular ticket.
+    fn matches(&self, ticket: &AbstractTicket) -> bool {
+        let matches_subsystem = match &self.subsystem {
             Some(ssts) => {
                 // Try to unwrap the result of the subsystems field only when a configured filter
                 // actually needs the subsystems. That way, subsystems are strictly optional,
@@ -340,46 +890,186 @@ impl config::Section {
                     }
                 };
 
-                ssts.iter()
-                    // Compare both subsystems in lower case.
-                    // Match if any of the ticket SSTs matches any of the template SSTs.
-                    .any(|sst| {
-                        unwrapped_ssts
-                            .iter()
-                            .any(|ticket_sst| sst.to_lowercase() == ticket_sst.to_lowercase())
-                    })
+                // Match if any of the ticket SSTs matches any of the template SSTs.
+                matches_values(Some(ssts), &as_strs(unwrapped_ssts))
             }
             // If the filter doesn't configure a subsystem, match by default
             None => true,
         };
-        let matches_component = match &self.filter.component {
-            Some(components) => components
+
+        // Bugzilla flags have the `name: value` format. Enable matching the name alone, too.
+        let flags: Vec<&str> = ticket
+            .flags
+            .iter()
+            .flatten()
+            .flat_map(|flag| {
+                let name = flag.split(": ").next().unwrap_or(flag);
+                [flag.as_str(), name]
+            })
+            .collect();
+
+        let matches_public = match self.public {
+            Some(public) => public == ticket.public,
+            // If the filter doesn't configure the visibility, match by default
+            None => true,
+        };
+
+        let matches_any = match &self.any {
+            Some(filters) => filters.iter().any(|filter| filter.matches(ticket)),
+            // If the filter doesn't configure any alternatives, match by default
+            None => true,
+        };
+        let matches_all = match &self.all {
+            Some(filters) => filters.iter().all(|filter| filter.matches(ticket)),
+            // If the filter doesn't configure any requirements, match by default
+            None => true,
+        };
+        let matches_not = match &self.not {
+            Some(filter) => !filter.matches(ticket),
+            // If the filter doesn't configure a negation, match by default
+            None => true,
+        };
+
+        // For each property, match if any of the ticket values matches any of the template values.
+        // If the filter doesn't configure the property, match by default.
+        matches_values(self.doc_type.as_deref(), &[ticket.doc_type.as_str()])
+            && matches_subsystem
+            && matches_values(self.component.as_deref(), &as_strs(&ticket.components))
+            && matches_values(
+                self.label.as_deref(),
+                &as_strs(ticket.labels.as_deref().unwrap_or_default()),
+            )
+            && matches_values(self.flag.as_deref(), &flags)
+            && matches_values(self.priority.as_deref(), &[ticket.priority.as_str()])
+            && matches_values(self.product.as_deref(), &[ticket.product.as_str()])
+            && matches_values(
+                self.target_release.as_deref(),
+                &as_strs(&ticket.target_releases),
+            )
+            && matches_values(self.status.as_deref(), &[ticket.status.as_str()])
+            && matches_public
+            && matches_any
+            && matches_all
+            && matches_not
+    }
+}
+
+/// Checks whether any of the patterns configured in a filter property matches
+/// any of the ticket values. Ignores letter case.
+/// If the filter doesn't configure the property, match by default.
+fn matches_values(configured: Option<&[config::Pattern]>, ticket_values: &[&str]) -> bool {
+    match configured {
+        Some(patterns) => patterns.iter().any(|pattern| {
+            ticket_values
                 .iter()
-                // Compare both components in lower case
-                // Match if any of the ticket SSTs matches any of the template SSTs.
-                .any(|cmp| {
-                    ticket
-                        .components
+                .any(|ticket_value| pattern.is_match(ticket_value))
+        }),
+        None => true,
+    }
+}
+
+/// Borrow a list of strings as a list of string slices.
+fn as_strs(values: &[String]) -> Vec<&str> {
+    values.iter().map(String::as_str).collect()
+}
+
+/// Known priority names from the most to the least severe, across trackers.
+const PRIORITY_RANKS: [&str; 10] = [
+    "blocker", "urgent", "critical", "high", "major", "medium", "normal", "minor", "low", "trivial",
+];
+
+impl config::SortKey {
+    /// Compare two tickets by this key, in the configured direction.
+    fn compare(self, a: &AbstractTicket, b: &AbstractTicket) -> Ordering {
+        let ordering = match self.field {
+            config::SortField::Component => {
+                let component = |t: &AbstractTicket| t.components.first().map(|c| c.to_lowercase());
+                component(a).cmp(&component(b))
+            }
+            // The order goes up in severity, so that `priority desc` lists blockers first.
+            // Unknown priorities are the least severe.
+            config::SortField::Priority => {
+                let severity = |t: &AbstractTicket| {
+                    let priority = t.priority.to_lowercase();
+                    PRIORITY_RANKS
                         .iter()
-                        .any(|ticket_cmp| cmp.to_lowercase() == ticket_cmp.to_lowercase())
-                }),
-            // If the filter doesn't configure a component, match by default
-            None => true,
+                        .position(|&known| known == priority)
+                        .map_or(0, |rank| PRIORITY_RANKS.len() - rank)
+                };
+                severity(a).cmp(&severity(b))
+            }
+            config::SortField::Summary => a.summary.to_lowercase().cmp(&b.summary.to_lowercase()),
+            config::SortField::Key => key_parts(&a.id.key).cmp(&key_parts(&b.id.key)),
+            config::SortField::DocType => a.doc_type.to_lowercase().cmp(&b.doc_type.to_lowercase()),
         };
 
-        matches_doc_type && matches_subsystem && matches_component
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
     }
 }
 
+/// Split a ticket key, such as `PROJECT-123`, into its prefix and its number,
+/// so that `PROJECT-9` sorts before `PROJECT-10`.
+fn key_parts(key: &str) -> (&str, Option<u64>) {
+    let prefix = key.trim_end_matches(|c: char| c.is_ascii_digit());
+    let number = key[prefix.len()..].parse().ok();
+
+    (prefix, number)
+}
+
+/// Fill in the file name pattern, where `{type}` is either `assembly` or `ref`.
+fn file_name_from_pattern(pattern: &str, module_type: &str, module_id: &str) -> String {
+    pattern
+        .replace("{type}", module_type)
+        .replace("{id}", module_id)
+}
+
 /// Form all modules that are recursively defined in the template configuration.
+///
+/// The last module is the appendix with the release notes that no section includes.
+/// It only has content in the internal variant.
+///
+/// Also returns the outline of all chapters that have content.
 pub fn format_document(
     tickets: &[&AbstractTicket],
     template: &config::Template,
     variant: DocumentVariant,
     with_priv_footnote: bool,
-) -> Vec<Module> {
-    // Prepare a container for ticket usage statistics.
-    let mut ticket_stats = HashMap::new();
+    custom_templates: &CustomTemplates,
+) -> Result<(Vec<Module>, Vec<Outline>)> {
+    let file_name_pattern = template
+        .file_name_pattern
+        .as_deref()
+        .unwrap_or(DEFAULT_FILE_NAME_PATTERN);
+
+    // Find the tickets that no regular section includes, so that catch-all sections
+    // can collect them, regardless of where in the document they are.
+    let mut placed = HashSet::new();
+    for section in &template.chapters {
+        section.record_placed(tickets, &mut placed);
+    }
+    let unplaced = tickets
+        .iter()
+        .map(|t| Rc::clone(&t.id))
+        .filter(|id| !placed.contains(id))
+        .collect();
+
+    let mut context = Context {
+        variant,
+        with_priv_footnote,
+        placement: template.placement,
+        file_name_pattern,
+        custom_templates,
+        unplaced,
+        // Prepare a container for ticket usage statistics.
+        ticket_stats: HashMap::new(),
+        file_names: declared_file_names(template, file_name_pattern)
+            .into_iter()
+            .collect(),
+    };
 
     // Initialize every ticket in the statistics with 0 usage.
     // Later, the number increases each time that the ticket is used.
@@ -387,34 +1077,156 @@ pub fn format_document(
     // is necessary for tickets that end up unused, because they wouldn't
     // call `entry` at all, and would report nothing.
     for ticket in tickets {
-        ticket_stats.insert(Rc::clone(&ticket.id), 0);
+        context.ticket_stats.insert(Rc::clone(&ticket.id), 0);
     }
 
     // TODO: If no release notes trickle down into a chapter, the chapter is simply skipped.
     // However, includes from the manual RN content tend to target all chapters.
     // Figure out a solution. Perhaps an empty file to appease the include from outside?
-    let chapters: Vec<_> = template
+    let (mut chapters, outlines): (Vec<_>, Vec<_>) = template
         .chapters
         .iter()
-        .map(|section| {
-            section.modules(
-                tickets,
-                None,
-                variant,
-                with_priv_footnote,
-                &mut ticket_stats,
-            )
-        })
-        .collect();
+        .map(|section| section.modules(tickets, None, &mut context))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+    let mut outline: Vec<Outline> = outlines.into_iter().flatten().collect();
     log::debug!("Chapters: {:#?}", chapters);
 
+    let (appendix, appendix_outline) = unplaced_appendix(tickets, &context)?;
+    chapters.push(appendix);
+    outline.extend(appendix_outline);
+
     // A crude way to ensure that the statistics are only printed once, and not twice.
     // TODO: Revisit, maybe return the value instead.
     if variant == DocumentVariant::Internal {
-        report_usage_statistics(&ticket_stats);
+        report_usage_statistics(&context.ticket_stats);
+        report_duplicate_file_names(template, file_name_pattern);
+    }
+
+    Ok((chapters, outline))
+}
+
+/// Prepare the appendix that lists the release notes that no section includes,
+/// so that writers can see what they forgot to categorize.
+///
+/// The appendix is blank in every variant except for the internal one.
+fn unplaced_appendix(
+    tickets: &[&AbstractTicket],
+    context: &Context<'_>,
+) -> Result<(Module, Option<Outline>)> {
+    let file_name = file_name_from_pattern(context.file_name_pattern, "ref", UNPLACED_ID);
+
+    let notes: Vec<_> = tickets
+        .iter()
+        .filter(|t| context.ticket_stats.get(&t.id) == Some(&0))
+        .map(|t| OutlineNote::new(t, None, context))
+        .collect();
+
+    if context.variant != DocumentVariant::Internal || notes.is_empty() {
+        return Ok((Module::Blank { file_name }, None));
+    }
+
+    let unused: Vec<String> = notes.iter().map(|note| note.text.clone()).collect();
+    let title = "Unplaced release notes";
+    let intro_abstract = "These release notes match no section in the templates.";
+
+    let template = Leaf {
+        id: UNPLACED_ID,
+        title,
+        intro_abstract,
+        release_notes: &unused,
+    };
+
+    let text = context
+        .custom_templates
+        .render(custom_templates::REFERENCE, &template)?;
+
+    let outline = Outline {
+        id: UNPLACED_ID.to_string(),
+        title: title.to_string(),
+        file_name: file_name.clone(),
+        intro_abstract: Some(intro_abstract.to_string()),
+        release_notes: notes,
+        sections: Vec::new(),
+    };
+
+    Ok((
+        Module::WithContent {
+            file_name,
+            // Mark the module as an appendix.
+            text: format!("{APPENDIX_ATTRIBUTE}\n{text}"),
+            included_modules: None,
+        },
+        Some(outline),
+    ))
+}
+
+/// List the section titles and abstracts in the template that translators can translate,
+/// in document order.
+pub fn translatable_texts(template: &config::Template) -> Vec<Message> {
+    let mut messages = Vec::new();
+    for section in &template.chapters {
+        section.translatable_texts(None, &mut messages);
+    }
+
+    messages
+}
+
+/// Prepare a copy of the template with translated section titles and abstracts.
+/// The generated IDs and file names stay the same as in the English document.
+pub fn localize(template: &config::Template, translations: &Translations) -> config::Template {
+    let mut localized = template.clone();
+    for section in &mut localized.chapters {
+        section.localize(None, translations);
     }
 
-    chapters
+    localized
+}
+
+/// List the file names of the sections in the templates, and of the appendix,
+/// in document order. The sections that `group_by` generates aren't known in advance.
+fn declared_file_names(template: &config::Template, pattern: &str) -> Vec<String> {
+    let mut file_names = Vec::new();
+    for section in &template.chapters {
+        section.record_file_names(None, pattern, &mut file_names);
+    }
+    // The appendix with unplaced release notes is always generated, even if blank.
+    file_names.push(file_name_from_pattern(pattern, "ref", UNPLACED_ID));
+
+    file_names
+}
+
+/// Find the file names that several sections share. Each of these sections
+/// would overwrite the generated file of the other.
+fn duplicate_file_names(template: &config::Template, pattern: &str) -> Vec<String> {
+    let file_names = declared_file_names(template, pattern);
+
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+    for file_name in file_names {
+        if seen.contains(&file_name) {
+            if !duplicates.contains(&file_name) {
+                duplicates.push(file_name);
+            }
+        } else {
+            seen.insert(file_name);
+        }
+    }
+
+    duplicates
+}
+
+/// Log a warning about file names that several sections share.
+fn report_duplicate_file_names(template: &config::Template, pattern: &str) {
+    let duplicates = duplicate_file_names(template, pattern);
+
+    if !duplicates.is_empty() {
+        log::warn!(
+            "Several sections in the templates produce the same file:\n\t {}",
+            duplicates.join(", ")
+        );
+    }
 }
 
 /// Log statistics about tickets that haven't been used anywhere in the templates,
@@ -434,6 +1246,7 @@ fn report_usage_statistics(ticket_stats: &HashMap<Rc<TicketId>, u32>) {
 
     if !unused.is_empty() {
         log::warn!("Tickets unused in the templates:\n\t {}", unused.join(", "));
+        log::warn!("The internal variant lists them in the unplaced release notes appendix.");
     }
 
     if !overused.is_empty() {
@@ -441,5 +1254,729 @@ fn report_usage_statistics(ticket_stats: &HashMap<Rc<TicketId>, u32>) {
             "Tickets used more than once in the templates:\n\t {}",
             overused.join(", ")
         );
+        log::warn!(
+            "To include each ticket only once, set `placement: first-match` in the templates."
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ticket_abstraction::tests::ticket;
+
+    /// Parse a filter from its YaML configuration.
+    fn filter(yaml: &str) -> config::Filter {
+        serde_yaml::from_str(yaml).expect("Invalid filter in a test.")
+    }
+
+    #[test]
+    fn combine_filters() {
+        let mut bug_fix = ticket("A-1");
+        bug_fix.components = vec!["kernel".to_string()];
+        bug_fix.subsystems = Ok(vec!["sst_networking".to_string()]);
+
+        let bug_fix_not_networking = filter(
+            r#"
+            doc_type: ["Bug Fix"]
+            component: ["kernel"]
+            not:
+              subsystem: ["sst_networking"]
+            "#,
+        );
+        assert!(!bug_fix_not_networking.matches(&bug_fix));
+
+        let bug_fix_or_known_issue = filter(
+            r#"
+            any:
+              - doc_type: ["Known Issue"]
+              - all:
+                  - doc_type: ["bug fix"]
+                  - component: ["kernel"]
+            "#,
+        );
+        assert!(bug_fix_or_known_issue.matches(&bug_fix));
+
+        // An empty filter matches all tickets.
+        assert!(filter("{}").matches(&bug_fix));
+    }
+
+    #[test]
+    fn filter_by_patterns() {
+        let mut python = ticket("A-3");
+        python.components = vec!["python3.12".to_string(), "nodejs-18".to_string()];
+
+        assert!(filter(r#"component: ["glob:Python3*"]"#).matches(&python));
+        assert!(filter(r"component: ['re:^nodejs-\d+$']").matches(&python));
+        assert!(!filter(r#"component: ["re:^kernel.*"]"#).matches(&python));
+        assert!(serde_yaml::from_str::<config::Filter>(r#"component: ["re:(unclosed"]"#).is_err());
+    }
+
+    #[test]
+    fn filter_by_ticket_fields() {
+        let bug_fix = ticket("A-1");
+
+        let mut highlight = ticket("A-2");
+        highlight.labels = Some(vec!["rn-highlight".to_string()]);
+        highlight.flags = Some(vec!["blocker: +".to_string()]);
+        highlight.priority = "Blocker".to_string();
+        highlight.product = "RHEL".to_string();
+        highlight.target_releases = vec!["9.4".to_string(), "9.5".to_string()];
+        highlight.status = "Verified".to_string();
+        highlight.public = false;
+
+        let highlights = filter(
+            r#"
+            label: ["RN-highlight"]
+            flag: ["blocker"]
+            priority: ["blocker"]
+            product: ["rhel"]
+            target_release: ["9.5"]
+            status: ["verified", "closed"]
+            public: false
+            "#,
+        );
+        assert!(highlights.matches(&highlight));
+        assert!(!highlights.matches(&bug_fix));
+        assert!(!filter("flag: [\"blocker: -\"]").matches(&highlight));
+        assert!(!filter("target_release: [\"9.6\"]").matches(&highlight));
+        assert!(!filter("status: [\"New\"]").matches(&highlight));
+        assert!(!filter("public: true").matches(&highlight));
+    }
+
+    /// Find a generated module by its file name, searching recursively.
+    fn find<'a>(modules: &'a [Module], file_name: &str) -> Option<&'a Module> {
+        modules.iter().find_map(|module| match module {
+            _ if module.file_name() == file_name => Some(module),
+            Module::WithContent {
+                included_modules: Some(included),
+                ..
+            } => find(included, file_name),
+            _ => None,
+        })
+    }
+
+    fn text(module: Option<&Module>) -> &str {
+        match module {
+            Some(Module::WithContent { text, .. }) => text,
+            _ => "",
+        }
+    }
+
+    #[test]
+    fn collect_remaining_tickets() {
+        let template: config::Template = serde_yaml::from_str(
+            r#"
+            chapters:
+              - title: "Bug fixes"
+                filter:
+                  doc_type: ["Bug Fix"]
+                subsections:
+                  - title: "Other bug fixes"
+                    catch_all: true
+                  - title: "Kernel"
+                    filter:
+                      component: ["kernel"]
+            "#,
+        )
+        .expect("Invalid template in a test.");
+
+        let kernel = ticket("A-1");
+        let mut installer = ticket("A-2");
+        installer.components = vec!["installer".to_string()];
+        let mut known_issue = ticket("A-3");
+        known_issue.doc_type = "Known Issue".to_string();
+        let tickets = [&kernel, &installer, &known_issue];
+
+        let (modules, _) = format_document(
+            &tickets,
+            &template,
+            DocumentVariant::Internal,
+            false,
+            &CustomTemplates::default(),
+        )
+        .expect("Failed to format the document.");
+
+        let other = text(find(&modules, "ref_bug-fixes-other-bug-fixes.adoc"));
+        assert!(other.contains("A-2"));
+        assert!(!other.contains("A-1"));
+
+        let unplaced = text(find(&modules, "ref_unplaced-release-notes.adoc"));
+        assert!(unplaced.starts_with("[appendix]"));
+        assert!(unplaced.contains("A-3"));
+        assert!(!unplaced.contains("A-2"));
+
+        let (modules, _) = format_document(
+            &tickets,
+            &template,
+            DocumentVariant::External,
+            false,
+            &CustomTemplates::default(),
+        )
+        .expect("Failed to format the document.");
+        assert_eq!(
+            find(&modules, "ref_unplaced-release-notes.adoc"),
+            Some(&Module::Blank {
+                file_name: "ref_unplaced-release-notes.adoc".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn place_tickets() {
+        let template = |placement: &str| -> config::Template {
+            serde_yaml::from_str(&format!(
+                r#"
+                placement: {placement}
+                chapters:
+                  - title: "Kernel"
+                    filter:
+                      component: ["kernel"]
+                  - title: "Bug fixes"
+                    filter:
+                      doc_type: ["Bug Fix"]
+                "#
+            ))
+            .expect("Invalid template in a test.")
+        };
+
+        let kernel = ticket("A-1");
+        let tickets = [&kernel];
+
+        let (modules, _) = format_document(
+            &tickets,
+            &template("first-match"),
+            DocumentVariant::Internal,
+            false,
+            &CustomTemplates::default(),
+        )
+        .expect("Failed to format the document.");
+        assert!(text(find(&modules, "ref_kernel.adoc")).contains("[id=\"Jira-A-1\"]"));
+        assert!(text(find(&modules, "ref_bug-fixes.adoc")).is_empty());
+
+        let (modules, _) = format_document(
+            &tickets,
+            &template("all"),
+            DocumentVariant::Internal,
+            false,
+            &CustomTemplates::default(),
+        )
+        .expect("Failed to format the document.");
+        assert!(text(find(&modules, "ref_kernel.adoc")).contains("[id=\"Jira-A-1\"]"));
+        assert!(text(find(&modules, "ref_bug-fixes.adoc")).contains("[id=\"Jira-A-1_bug-fixes\"]"));
+    }
+
+    #[test]
+    fn sort_tickets() {
+        let section: config::Section = serde_yaml::from_str(
+            r#"
+            title: "Bug fixes"
+            sort_by: ["component", "priority desc", "key"]
+            "#,
+        )
+        .expect("Invalid section in a test.");
+
+        let mut tickets = ["A-10", "A-9", "A-3", "A-2"].map(ticket);
+        tickets[0].priority = "Unprioritized".to_string();
+        tickets[1].priority = "Minor".to_string();
+        tickets[2].components = vec!["Installer".to_string()];
+
+        let sorted: Vec<_> = section
+            .sorted(tickets.iter().collect())
+            .iter()
+            .map(|t| t.id.key.as_str())
+            .collect();
+        // The most severe priority comes first, and unknown priorities come last.
+        assert_eq!(sorted, ["A-3", "A-2", "A-9", "A-10"]);
+
+        let ascending = config::SortKey {
+            field: config::SortField::Priority,
+            descending: false,
+        };
+        assert_eq!(ascending.compare(&tickets[1], &tickets[3]), Ordering::Less);
+
+        assert!(serde_yaml::from_str::<config::SortKey>("priority up").is_err());
+    }
+
+    #[test]
+    fn name_modules() {
+        let template: config::Template = serde_yaml::from_str(
+            r#"
+            file_name_pattern: "{id}.{type}.adoc"
+            chapters:
+              - title: "Bug fixes"
+                id: "fixed-issues"
+                subsections:
+                  - title: "Kernel"
+                    file_name: "kernel-fixes.adoc"
+                  - title: "Installer"
+              - title: "Fixed issues"
+              - title: "Unplaced release notes"
+                file_name: "ref_unplaced-release-notes.adoc"
+            "#,
+        )
+        .expect("Invalid template in a test.");
+
+        let installer = ticket("A-1");
+        let (modules, _) = format_document(
+            &[&installer],
+            &template,
+            DocumentVariant::Internal,
+            false,
+            &CustomTemplates::default(),
+        )
+        .expect("Failed to format the document.");
+
+        assert_eq!(modules[0].file_name(), "fixed-issues.assembly.adoc");
+        assert!(find(&modules, "kernel-fixes.adoc").is_some());
+        assert!(text(find(&modules, "fixed-issues-installer.ref.adoc"))
+            .contains("[id=\"fixed-issues-installer\"]"));
+
+        // The unplaced release notes appendix follows the pattern, too.
+        assert!(find(&modules, "unplaced-release-notes.ref.adoc").is_some());
+
+        assert_eq!(
+            duplicate_file_names(&template, "{id}.adoc"),
+            ["fixed-issues.adoc".to_string()]
+        );
+        // A section can't take the file name of the appendix.
+        assert_eq!(
+            duplicate_file_names(&template, "{type}_{id}.adoc"),
+            ["ref_unplaced-release-notes.adoc".to_string()]
+        );
+    }
+
+    /// The outline lists each copy of a release note with its own anchor.
+    #[test]
+    fn outline_document() {
+        let template: config::Template = serde_yaml::from_str(
+            r#"
+            chapters:
+              - title: "Bug fixes"
+                subsections:
+                  - title: "Kernel"
+                  - title: "All"
+              - title: "Empty"
+                filter:
+                  component: ["none"]
+            "#,
+        )
+        .expect("Invalid template in a test.");
+
+        let kernel = ticket("A-1");
+        let (_, outline) = format_document(
+            &[&kernel],
+            &template,
+            DocumentVariant::External,
+            false,
+            &CustomTemplates::default(),
+        )
+        .expect("Failed to format the document.");
+
+        // The empty chapter has no outline.
+        assert_eq!(outline.len(), 1);
+        assert_eq!(outline[0].id, "bug-fixes");
+        assert_eq!(
+            outline[0].sections[0].file_name,
+            "ref_bug-fixes-kernel.adoc"
+        );
+
+        let anchors: Vec<&str> = outline[0]
+            .sections
+            .iter()
+            .flat_map(|section| &section.release_notes)
+            .map(|note| note.anchor.as_str())
+            .collect();
+        assert_eq!(anchors, ["Jira-A-1", "Jira-A-1_bug-fixes-all"]);
+
+        let json = serde_json::to_value(&outline).expect("Failed to serialize the outline.");
+        let note = &json[0]["sections"][0]["release_notes"][0];
+        assert_eq!(note["ticket"]["id"], kernel.id.to_string());
+        assert_eq!(note["ticket"]["url"], kernel.url);
+    }
+
+    /// Only the variants with debugging information export the internal fields of tickets.
+    #[test]
+    fn hide_internal_ticket_fields() {
+        let template: config::Template =
+            serde_yaml::from_str("chapters:\n  - title: \"Bug fixes\"\n")
+                .expect("Invalid template in a test.");
+
+        let mut private = ticket("A-1");
+        private.public = false;
+        private.description = Some("Internal discussion.".to_string());
+        private.assignee = Some("developer@host.org".to_string());
+        private.groups = Some(vec!["secret-group".to_string()]);
+        private.flags = Some(vec!["blocker+".to_string()]);
+
+        let ticket_json = |variant| {
+            let (_, outline) = format_document(
+                &[&private],
+                &template,
+                variant,
+                false,
+                &CustomTemplates::default(),
+            )
+            .expect("Failed to format the document.");
+            let json = serde_json::to_value(&outline).expect("Failed to serialize the outline.");
+            json[0]["release_notes"][0]["ticket"].clone()
+        };
+        let internal_fields = [
+            "description",
+            "assignee",
+            "docs_contact",
+            "groups",
+            "flags",
+            "status",
+        ];
+
+        for variant in [
+            DocumentVariant::External,
+            DocumentVariant::Custom { debug_info: false },
+        ] {
+            let ticket = ticket_json(variant);
+            assert_eq!(ticket["id"], private.id.to_string());
+            assert_eq!(ticket["doc_text"], private.doc_text);
+            // A private ticket has no link.
+            assert!(ticket["url"].is_null());
+            for field in internal_fields {
+                assert!(
+                    ticket.get(field).is_none(),
+                    "The {field} field is exported."
+                );
+            }
+        }
+
+        let ticket = ticket_json(DocumentVariant::Internal);
+        assert_eq!(ticket["url"], private.url);
+        for field in internal_fields {
+            assert!(ticket.get(field).is_some(), "The {field} field is missing.");
+        }
+    }
+
+    #[test]
+    fn group_by_subheadings() {
+        let template: config::Template = serde_yaml::from_str(
+            r#"
+            chapters:
+              - title: "Bug fixes"
+                subheadings: component
+            "#,
+        )
+        .expect("Invalid template in a test.");
+
+        let mut podman = ticket("A-1");
+        podman.components = vec!["podman".to_string()];
+        let mut kernel = ticket("A-2");
+        kernel.components = vec!["kernel".to_string(), "podman".to_string()];
+        let mut no_component = ticket("A-3");
+        no_component.components = Vec::new();
+        // These values look like the subheading with no value, or like each other.
+        let mut other = ticket("A-4");
+        other.components = vec!["Other".to_string()];
+        let mut spaced = ticket("A-5");
+        spaced.components = vec!["foo bar".to_string()];
+        let mut dashed = ticket("A-6");
+        dashed.components = vec!["foo-bar".to_string()];
+        // This value differs from another one only in case.
+        let mut capitalized = ticket("A-7");
+        capitalized.components = vec!["Kernel".to_string()];
+
+        let (modules, outline) = format_document(
+            &[
+                &podman,
+                &kernel,
+                &no_component,
+                &other,
+                &spaced,
+                &dashed,
+                &capitalized,
+            ],
+            &template,
+            DocumentVariant::External,
+            false,
+            &CustomTemplates::default(),
+        )
+        .expect("Failed to format the document.");
+
+        let text = text(find(&modules, "ref_bug-fixes.adoc"));
+        let position = |needle: &str| text.find(needle).expect("Missing text in the module.");
+
+        // Each ticket appears only under the subheading of its first component.
+        assert_eq!(text.matches("[discrete]").count(), 6);
+        assert!(position("== kernel") < position("[id=\"Jira-A-2\"]"));
+        assert!(position("[id=\"Jira-A-2\"]") < position("== podman"));
+        assert!(position("== podman") < position("[id=\"Jira-A-1\"]"));
+        // Values that differ only in case share the subheading of the first spelling.
+        assert!(!text.contains("== Kernel"));
+        assert!(position("== kernel") < position("[id=\"Jira-A-7\"]"));
+        assert!(position("[id=\"Jira-A-7\"]") < position("== podman"));
+        assert!(
+            position("[id=\"bug-fixes_no-component\"]\n== Other") < position("[id=\"Jira-A-3\"]")
+        );
+
+        // Every subheading has a unique ID.
+        assert!(position("[id=\"bug-fixes_other\"]\n== Other") < position("[id=\"Jira-A-4\"]"));
+        assert!(position("[id=\"bug-fixes_foo-bar\"]\n== foo bar") < position("[id=\"Jira-A-5\"]"));
+        assert!(
+            position("[id=\"bug-fixes_foo-bar-2\"]\n== foo-bar") < position("[id=\"Jira-A-6\"]")
+        );
+
+        // The outline follows the order of the module.
+        let anchors: Vec<&str> = outline[0]
+            .release_notes
+            .iter()
+            .map(|note| note.anchor.as_str())
+            .collect();
+        assert_eq!(
+            anchors,
+            ["Jira-A-5", "Jira-A-6", "Jira-A-2", "Jira-A-7", "Jira-A-4", "Jira-A-1", "Jira-A-3"]
+        );
+    }
+
+    #[test]
+    fn generate_grouped_sections() {
+        let template: config::Template = serde_yaml::from_str(
+            r#"
+            chapters:
+              - title: "Bug fixes"
+                group_by: subsystem
+                group_title: "{value} updates"
+                other_title: "General"
+                subheadings: component
+            "#,
+        )
+        .expect("Invalid template in a test.");
+
+        let mut networking = ticket("A-1");
+        networking.subsystems = Ok(vec!["sst_networking".to_string()]);
+        let mut kernel = ticket("A-2");
+        kernel.subsystems = Ok(vec!["sst_kernel".to_string(), "SST_Networking".to_string()]);
+        let no_subsystem = ticket("A-3");
+
+        let (modules, outline) = format_document(
+            &[&networking, &kernel, &no_subsystem],
+            &template,
+            DocumentVariant::External,
+            false,
+            &CustomTemplates::default(),
+        )
+        .expect("Failed to format the document.");
+
+        let assembly = text(find(&modules, "assembly_bug-fixes.adoc"));
+        assert!(assembly.contains("include::ref_bug-fixes-sst-kernel.adoc[leveloffset=+1]"));
+
+        // The IDs come from the values, and the titles from the pattern.
+        let sections: Vec<(&str, &str)> = outline[0]
+            .sections
+            .iter()
+            .map(|section| (section.id.as_str(), section.title.as_str()))
+            .collect();
+        assert_eq!(
+            sections,
+            [
+                ("bug-fixes-sst-kernel", "sst_kernel updates"),
+                ("bug-fixes-sst-networking", "sst_networking updates"),
+                ("bug-fixes-no-subsystem", "General updates"),
+            ]
+        );
+
+        let networking_module = text(find(&modules, "ref_bug-fixes-sst-networking.adoc"));
+        assert!(networking_module.contains("[id=\"Jira-A-1\"]"));
+        assert!(networking_module.contains("[id=\"Jira-A-2_bug-fixes-sst-networking\"]"));
+        // The generated sections inherit the subheadings.
+        assert!(networking_module.contains("== kernel"));
+
+        let other_module = text(find(&modules, "ref_bug-fixes-no-subsystem.adoc"));
+        assert!(other_module.contains("[id=\"Jira-A-3\"]"));
+        assert!(!other_module.contains("Jira-A-1"));
+    }
+
+    #[test]
+    fn keep_generated_sections_unique() {
+        let template: config::Template = serde_yaml::from_str(
+            r#"
+            chapters:
+              - title: "Bug fixes"
+                group_by: component
+            "#,
+        )
+        .expect("Invalid template in a test.");
+
+        // These values look like the subsection with no value, or like each other.
+        let mut other = ticket("A-1");
+        other.components = vec!["other".to_string()];
+        let mut no_component = ticket("A-2");
+        no_component.components = Vec::new();
+        let mut underscore = ticket("A-3");
+        underscore.components = vec!["sst_kernel".to_string()];
+        let mut dash = ticket("A-4");
+        dash.components = vec!["sst-kernel".to_string()];
+
+        let (modules, outline) = format_document(
+            &[&other, &no_component, &underscore, &dash],
+            &template,
+            DocumentVariant::External,
+            false,
+            &CustomTemplates::default(),
+        )
+        .expect("Failed to format the document.");
+
+        let ids: Vec<&str> = outline[0]
+            .sections
+            .iter()
+            .map(|section| section.id.as_str())
+            .collect();
+        assert_eq!(
+            ids,
+            [
+                "bug-fixes-other",
+                "bug-fixes-sst-kernel",
+                "bug-fixes-sst-kernel-2",
+                "bug-fixes-no-component",
+            ]
+        );
+        assert!(text(find(&modules, "ref_bug-fixes-sst-kernel-2.adoc")).contains("Jira-A-3"));
+        assert!(text(find(&modules, "ref_bug-fixes-no-component.adoc")).contains("Jira-A-2"));
+
+        // A generated section can't overwrite the file of a section in the templates.
+        let template: config::Template = serde_yaml::from_str(
+            r#"
+            chapters:
+              - title: "Bug fixes"
+                group_by: component
+              - title: "Kernel"
+                file_name: "ref_bug-fixes-sst-kernel.adoc"
+            "#,
+        )
+        .expect("Invalid template in a test.");
+
+        assert!(format_document(
+            &[&underscore],
+            &template,
+            DocumentVariant::External,
+            false,
+            &CustomTemplates::default(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn select_custom_variant() {
+        let variant: config::Variant = serde_yaml::from_str(
+            r"
+            name: partner-preview
+            filter:
+              public: true
+            include_unapproved: true
+            debug_info: true
+            ",
+        )
+        .expect("Invalid variant in a test.");
+
+        let approved = ticket("A-1");
+        let mut in_progress = ticket("A-2");
+        in_progress.doc_text_status = DocTextStatus::InProgress;
+        let mut private = ticket("A-3");
+        private.public = false;
+
+        assert!(variant.includes(&approved));
+        assert!(variant.includes(&in_progress));
+        assert!(!variant.includes(&private));
+        assert_eq!(variant.directory(), "partner-preview");
+
+        let external = config::Variant::builtin("external", false);
+        assert!(!external.includes(&in_progress));
+        assert!(external.includes(&private));
+
+        // A custom variant with debugging information adds the debug line, but no unplaced appendix.
+        let document_variant = DocumentVariant::Custom {
+            debug_info: variant.debug_info,
+        };
+        let note = in_progress.release_note(document_variant, false, None);
+        assert!(note.contains(&in_progress.url));
+
+        let template: config::Template = serde_yaml::from_str(
+            r#"
+            chapters:
+              - title: "Known issues"
+                filter:
+                  doc_type: ["Known Issue"]
+            "#,
+        )
+        .expect("Invalid template in a test.");
+        let (modules, _) = format_document(
+            &[&in_progress],
+            &template,
+            document_variant,
+            false,
+            &CustomTemplates::default(),
+        )
+        .expect("Failed to format the document.");
+        assert!(!modules.iter().any(Module::has_content));
+    }
+
+    #[test]
+    fn localize_template() {
+        let template: config::Template = serde_yaml::from_str(
+            r#"
+            chapters:
+              - title: "Bug fixes"
+                intro_abstract: "Fixed issues."
+                subsections:
+                  - title: "Kernel"
+                    subheadings: component
+            "#,
+        )
+        .expect("Invalid template in a test.");
+
+        let po = r#"
+msgctxt "section/bug-fixes/title"
+msgid "Bug fixes"
+msgstr "バグ修正"
+
+msgctxt "section/bug-fixes-kernel/title"
+msgid "Kernel"
+msgstr "カーネル"
+
+msgctxt "section/bug-fixes-kernel/other_title"
+msgid "Other"
+msgstr "その他"
+"#;
+        let translations = Translations::parse(po, "ja").expect("Failed to parse translations.");
+        let localized = localize(&template, &translations);
+
+        let chapter = &localized.chapters[0];
+        assert_eq!(chapter.title, "バグ修正");
+        // The untranslated abstract stays in English.
+        assert_eq!(chapter.intro_abstract.as_deref(), Some("Fixed issues."));
+        // The subheading with no value has a default title, which is translatable, too.
+        let kernel = &chapter
+            .subsections
+            .as_ref()
+            .expect("The subsections are missing.")[0];
+        assert_eq!(kernel.other_title(), "その他");
+
+        // The translated sections keep their English IDs and file names.
+        let mut file_names = Vec::new();
+        chapter.record_file_names(None, DEFAULT_FILE_NAME_PATTERN, &mut file_names);
+        assert_eq!(
+            file_names,
+            ["assembly_bug-fixes.adoc", "ref_bug-fixes-kernel.adoc"]
+        );
+
+        let contexts: Vec<String> = translatable_texts(&template)
+            .into_iter()
+            .map(|message| message.context)
+            .collect();
+        assert_eq!(
+            contexts,
+            [
+                "section/bug-fixes/title",
+                "section/bug-fixes/intro_abstract",
+                "section/bug-fixes-kernel/title",
+                "section/bug-fixes-kernel/other_title"
+            ]
+        );
     }
 }
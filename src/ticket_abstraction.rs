@@ -16,6 +16,7 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use std::collections::HashMap;
 use std::fmt;
 use std::rc::Rc;
 use std::string::ToString;
@@ -29,6 +30,7 @@ use serde::{Serialize, Serializer};
 
 use crate::config::{tracker, TicketQuery};
 use crate::extra_fields::{DocTextStatus, DocsContact, ExtraFields};
+use crate::markup;
 use crate::tracker_access::{self, AnnotatedTicket};
 
 /// An abstract ticket representation that generalizes over Bugzilla, Jira, and any other issue trackers.
@@ -70,8 +72,8 @@ impl Serialize for AbstractTicket {
     where
         S: Serializer,
     {
-        // 3 is the number of fields in the struct.
-        let mut state = serializer.serialize_struct("Color", 3)?;
+        // 22 is the number of serialized fields.
+        let mut state = serializer.serialize_struct("AbstractTicket", 22)?;
         state.serialize_field("id", &self.id.to_string())?;
         state.serialize_field("summary", &self.summary)?;
         state.serialize_field("description", &self.description)?;
@@ -80,6 +82,7 @@ impl Serialize for AbstractTicket {
         state.serialize_field("docs_contact", &self.docs_contact.as_str())?;
         state.serialize_field("doc_text_status", &self.doc_text_status.to_string())?;
         state.serialize_field("status", &self.status)?;
+        state.serialize_field("resolution", &self.resolution)?;
         state.serialize_field("is_open", &self.is_open)?;
         state.serialize_field("priority", &self.priority)?;
         state.serialize_field("url", &self.url)?;
@@ -97,6 +100,43 @@ impl Serialize for AbstractTicket {
     }
 }
 
+/// The fields of a ticket that a published document can show. It leaves out the fields
+/// that only the team that prepares the release notes needs, such as the docs contact,
+/// and the link to a private ticket.
+#[derive(Serialize)]
+pub struct PublicTicket<'a> {
+    id: String,
+    url: Option<&'a str>,
+    doc_type: &'a str,
+    doc_text: &'a str,
+    components: &'a [String],
+    product: &'a str,
+    target_releases: &'a [String],
+    subsystems: &'a [String],
+    public: bool,
+    references: Option<&'a [String]>,
+}
+
+impl AbstractTicket {
+    /// Only the fields of this ticket that a published document can show.
+    #[must_use]
+    pub fn public_fields(&self) -> PublicTicket<'_> {
+        PublicTicket {
+            id: self.id.to_string(),
+            url: self.public.then_some(self.url.as_str()),
+            doc_type: &self.doc_type,
+            doc_text: &self.doc_text,
+            components: &self.components,
+            product: &self.product,
+            target_releases: &self.target_releases,
+            // A ticket whose subsystems failed to load counts as having none.
+            subsystems: self.subsystems.as_deref().unwrap_or_default(),
+            public: self.public,
+            references: self.references.as_deref(),
+        }
+    }
+}
+
 /// An identification of the original ticket on the issue tracker.
 #[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
 pub struct TicketId {
@@ -135,7 +175,7 @@ impl IntoAbstract for Bug {
             // TODO: Find out how to get the bug description from comment#0 with Bugzilla
             description: None,
             doc_type: self.doc_type(bz_fields)?,
-            doc_text: self.doc_text(bz_fields)?,
+            doc_text: markup::to_asciidoc(&self.doc_text(bz_fields)?, bz_fields.doc_text_format),
             target_releases: self.target_releases(bz_fields),
             subsystems: self.subsystems(bz_fields).map_err(|e| e.to_string()),
             doc_text_status: self.doc_text_status(bz_fields),
@@ -175,7 +215,10 @@ impl IntoAbstract for Issue {
         let jira_fields = &config.jira;
         let ticket = AbstractTicket {
             doc_type: self.doc_type(jira_fields)?,
-            doc_text: self.doc_text(jira_fields)?,
+            doc_text: markup::to_asciidoc(
+                &self.doc_text(jira_fields)?,
+                jira_fields.doc_text_format,
+            ),
             // The target release is non-essential. Discard the error and store as Option.
             target_releases: self.target_releases(jira_fields),
             doc_text_status: self.doc_text_status(jira_fields),
@@ -240,18 +283,24 @@ pub fn from_queries(
 }
 
 /// Sort tickets to the order specified in the tickets configuration file.
+///
+/// If several queries return the same ticket, the ticket appears only once,
+/// at the position of the first query that returned it. If the queries configure
+/// different overrides for the ticket, report the conflict as an error.
 pub fn sort_tickets(
     queries: &[Arc<TicketQuery>],
     tickets: &[AnnotatedTicket],
 ) -> Result<Vec<AnnotatedTicket>> {
     let mut sorted_tickets: Vec<AnnotatedTicket> = Vec::new();
+    // The position of each ticket in the sorted Vec, so that duplicates can find their first occurrence.
+    let mut positions: HashMap<Rc<TicketId>, usize> = HashMap::new();
 
     // Go query by query. Queries are still sorted the same as in the config file. Use their order.
     for query in queries {
         // Find the indices of all tickets that match this query.
         // We're dealing with indices because that enables us to move a ticket from one Vec to another
         // using the Vec::swap_remove method, which takes an index as its argument.
-        let mut matching_tickets: Vec<AnnotatedTicket> = tickets
+        let matching_tickets: Vec<AnnotatedTicket> = tickets
             .iter()
             .filter(|at| query == &at.query)
             // TODO: Revisit whether this clone is necessary.
@@ -265,9 +314,140 @@ pub fn sort_tickets(
             bail!("Query produced no tickets: {:#?}", query);
         }
 
-        // Insert tickets that match this query into the sorted Vec.
-        sorted_tickets.append(&mut matching_tickets);
+        // Insert tickets that match this query into the sorted Vec, unless an earlier query
+        // already inserted them.
+        for annotated in matching_tickets {
+            if let Some(&position) = positions.get(&annotated.ticket.id) {
+                let first = &mut sorted_tickets[position];
+                merge_duplicate(first, annotated)?;
+            } else {
+                positions.insert(Rc::clone(&annotated.ticket.id), sorted_tickets.len());
+                sorted_tickets.push(annotated);
+            }
+        }
     }
 
     Ok(sorted_tickets)
 }
+
+/// Reconcile a ticket that several queries returned. The ticket keeps the position
+/// of the first query, but if only the later query configures overrides,
+/// use the ticket with the overrides applied.
+fn merge_duplicate(first: &mut AnnotatedTicket, duplicate: AnnotatedTicket) -> Result<()> {
+    log::debug!(
+        "Ticket {} is returned by several queries. Keeping the first one.",
+        first.ticket.id
+    );
+
+    match (&first.query.overrides, &duplicate.query.overrides) {
+        (Some(first_overrides), Some(duplicate_overrides))
+            if first_overrides != duplicate_overrides =>
+        {
+            bail!(
+                "Ticket {} is returned by several queries with conflicting overrides:\n{:#?}\n{:#?}",
+                first.ticket.id,
+                first.query,
+                duplicate.query
+            );
+        }
+        (None, Some(_)) => {
+            *first = duplicate;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::{KeyOrSearch, Overrides};
+
+    /// A minimal Jira ticket for unit tests. Adjust the fields that the test needs.
+    pub(crate) fn ticket(key: &str) -> AbstractTicket {
+        AbstractTicket {
+            id: Rc::new(TicketId {
+                key: key.to_string(),
+                tracker: tracker::Service::Jira,
+            }),
+            summary: format!("Summary of {key}"),
+            description: None,
+            doc_type: "Bug Fix".to_string(),
+            doc_text: ".A title\n\nA release note.".to_string(),
+            docs_contact: DocsContact(None),
+            status: "Closed".to_string(),
+            resolution: None,
+            is_open: false,
+            priority: "Major".to_string(),
+            url: format!("https://jira.host.org/browse/{key}"),
+            assignee: None,
+            components: vec!["kernel".to_string()],
+            product: "Product".to_string(),
+            labels: Some(Vec::new()),
+            flags: None,
+            target_releases: Vec::new(),
+            subsystems: Ok(Vec::new()),
+            groups: None,
+            public: true,
+            doc_text_status: DocTextStatus::Approved,
+            references: None,
+        }
+    }
+
+    /// A search query with optional doc type overrides.
+    fn query(search: &str, doc_type: Option<&str>) -> Arc<TicketQuery> {
+        Arc::new(TicketQuery {
+            tracker: tracker::Service::Jira,
+            using: KeyOrSearch::Search(search.to_string()),
+            overrides: doc_type.map(|doc_type| Overrides {
+                doc_type: Some(doc_type.to_string()),
+                components: None,
+                subsystems: None,
+            }),
+            references: Vec::new(),
+        })
+    }
+
+    fn annotated(key: &str, query: &Arc<TicketQuery>) -> AnnotatedTicket {
+        let mut annotated = AnnotatedTicket {
+            ticket: ticket(key),
+            query: Arc::clone(query),
+        };
+        annotated.override_fields();
+        annotated
+    }
+
+    #[test]
+    fn deduplicate_tickets_from_several_queries() {
+        let first = query("first", None);
+        let second = query("second", Some("Known Issue"));
+        let queries = [Arc::clone(&first), Arc::clone(&second)];
+
+        let tickets = [
+            annotated("A-2", &second),
+            annotated("A-1", &first),
+            annotated("A-2", &first),
+            annotated("A-3", &second),
+        ];
+
+        let sorted = sort_tickets(&queries, &tickets).expect("Failed to sort tickets.");
+        let keys: Vec<&str> = sorted.iter().map(|at| at.ticket.id.key.as_str()).collect();
+
+        // The duplicate keeps the position of the first query...
+        assert_eq!(keys, ["A-1", "A-2", "A-3"]);
+        // ...but it uses the overrides that only the second query configures.
+        assert_eq!(sorted[1].ticket.doc_type, "Known Issue");
+    }
+
+    #[test]
+    fn report_conflicting_overrides() {
+        let first = query("first", Some("Bug Fix"));
+        let second = query("second", Some("Known Issue"));
+        let queries = [Arc::clone(&first), Arc::clone(&second)];
+
+        let tickets = [annotated("A-1", &first), annotated("A-1", &second)];
+
+        assert!(sort_tickets(&queries, &tickets).is_err());
+    }
+}
@@ -0,0 +1,500 @@
+/*
+acorns: Generate an AsciiDoc release notes document from tracking tickets.
+Copyright (C) 2022  Marek Suchánek  <msuchane@redhat.com>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Export the translatable texts of the document as a gettext PO template,
+//! and read the translated PO files back for a localized build.
+//!
+//! Every text has a stable context key, which identifies it regardless of the language:
+//!
+//! * `section/{id}/title`, `section/{id}/intro_abstract`, `section/{id}/group_title`,
+//!   and `section/{id}/other_title` for sections, where `{id}` is the ID of the English module.
+//! * `ticket/{id}/doc_text` for release notes, where `{id}` is the ticket ID.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::mem;
+use std::path::Path;
+
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+
+use crate::config::Template;
+use crate::templating;
+use crate::ticket_abstraction::AbstractTicket;
+
+/// A text that translators can translate.
+#[derive(Debug, PartialEq)]
+pub struct Message {
+    /// The stable key of the text.
+    pub context: String,
+    /// A note for translators that describes where the text appears.
+    pub comment: String,
+    /// The English text.
+    pub source: String,
+}
+
+impl Message {
+    /// A text that belongs to a section of the template.
+    pub fn section(id: &str, field: &str, source: &str) -> Self {
+        Self {
+            context: format!("section/{id}/{field}"),
+            comment: format!("The {} of the {id} section.", field.replace('_', " ")),
+            source: source.to_string(),
+        }
+    }
+
+    /// The doc text of a ticket.
+    fn ticket(ticket: &AbstractTicket) -> Self {
+        Self {
+            context: format!("ticket/{}/doc_text", ticket.id),
+            comment: format!("The release note of {}: {}", ticket.id, ticket.summary),
+            source: ticket.doc_text.clone(),
+        }
+    }
+}
+
+/// A translated text, with the English text that the translator worked from.
+#[derive(Debug)]
+struct Translation {
+    source: String,
+    text: String,
+}
+
+/// The translated texts of a single language.
+#[derive(Debug)]
+pub struct Translations {
+    lang: String,
+    translations: HashMap<String, Translation>,
+}
+
+impl Translations {
+    /// Load the `{lang}.po` file from the translations directory.
+    pub fn load(translations_dir: &Path, lang: &str) -> Result<Self> {
+        // The language code becomes part of file names, so accept only simple codes such as `ja` or `zh-CN`.
+        if lang.is_empty()
+            || !lang
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!("Invalid language code: {lang}");
+        }
+
+        let po_file = translations_dir.join(format!("{lang}.po"));
+        log::debug!("Translation file: {}", po_file.display());
+
+        let text = fs::read_to_string(&po_file)
+            .wrap_err_with(|| format!("Cannot read the translation file: {}", po_file.display()))?;
+
+        Self::parse(&text, lang).wrap_err("Cannot parse the translation file.")
+    }
+
+    /// Parse the content of a PO file. Entries that are fuzzy, or that have
+    /// an empty translation, count as untranslated.
+    pub fn parse(text: &str, lang: &str) -> Result<Self> {
+        let translations = parse_entries(text)?
+            .into_iter()
+            .filter(|entry| !entry.fuzzy && !entry.text.is_empty())
+            // Entries without a context, such as the header, aren't texts of the document.
+            .filter_map(|entry| {
+                entry.context.map(|context| {
+                    (
+                        context,
+                        Translation {
+                            source: entry.id,
+                            text: entry.text,
+                        },
+                    )
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            lang: lang.to_string(),
+            translations,
+        })
+    }
+
+    /// The translation of the text with this context. Returns `None` if the text
+    /// isn't translated, or if the translation comes from a different English text.
+    pub fn get(&self, context: &str, source: &str) -> Option<&str> {
+        self.translations
+            .get(context)
+            .filter(|translation| translation.source == source)
+            .map(|translation| translation.text.as_str())
+    }
+
+    /// Log how many of the texts in the document have an up-to-date translation.
+    pub fn report_coverage(&self, messages: &[Message]) {
+        let lang = &self.lang;
+        let mut untranslated = Vec::new();
+        let mut outdated = Vec::new();
+
+        for message in messages {
+            match self.translations.get(&message.context) {
+                Some(translation) if translation.source == message.source => {}
+                Some(_) => outdated.push(message.context.as_str()),
+                None => untranslated.push(message.context.as_str()),
+            }
+        }
+
+        let total = messages.len();
+        let translated = total - untranslated.len() - outdated.len();
+        // An empty document has nothing left to translate.
+        let percent = (translated * 100).checked_div(total).unwrap_or(100);
+
+        log::info!("Translation coverage ({lang}): {translated} of {total} texts, {percent}%.");
+
+        if !untranslated.is_empty() {
+            log::warn!(
+                "Texts without a translation, which remain in English:\n\t {}",
+                untranslated.join(", ")
+            );
+        }
+
+        if !outdated.is_empty() {
+            log::warn!(
+                "Texts whose English source changed since the translation, which remain in English:\n\t {}",
+                outdated.join(", ")
+            );
+        }
+    }
+}
+
+/// List all translatable texts of the document: the section titles and abstracts
+/// in the template, followed by the doc texts of the tickets.
+pub fn messages(template: &Template, tickets: &[AbstractTicket]) -> Vec<Message> {
+    let mut contexts = HashSet::new();
+
+    templating::translatable_texts(template)
+        .into_iter()
+        .chain(tickets.iter().map(Message::ticket))
+        .filter(|message| !message.source.trim().is_empty())
+        // Sections that share an ID also share their texts.
+        .filter(|message| contexts.insert(message.context.clone()))
+        .collect()
+}
+
+/// Replace the doc texts of the tickets with their translations, where available.
+pub fn localize_tickets(
+    tickets: &[AbstractTicket],
+    translations: &Translations,
+) -> Vec<AbstractTicket> {
+    tickets
+        .iter()
+        .map(|ticket| {
+            let mut ticket = ticket.clone();
+            let message = Message::ticket(&ticket);
+            if let Some(text) = translations.get(&message.context, &message.source) {
+                ticket.doc_text = text.to_string();
+            }
+            ticket
+        })
+        .collect()
+}
+
+/// Format the texts as a PO template, which translators copy to `{lang}.po` and translate.
+pub fn po_template(messages: &[Message]) -> String {
+    let mut lines = vec![
+        "# The translatable texts of the release notes.".to_string(),
+        "# Save the translated copy as `translations/LANG.po` in the configuration directory."
+            .to_string(),
+        "msgid \"\"".to_string(),
+        "msgstr \"\"".to_string(),
+        "\"Content-Type: text/plain; charset=UTF-8\\n\"".to_string(),
+    ];
+
+    for message in messages {
+        lines.push(String::new());
+        lines.push(format!("#. {}", message.comment));
+        lines.push(format!("msgctxt {}", quote(&message.context)));
+        lines.push(format!("msgid {}", quote(&message.source)));
+        lines.push("msgstr \"\"".to_string());
+    }
+
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// Quote the text as a PO string. Multi-line texts start with an empty string,
+/// followed by one string per line.
+fn quote(text: &str) -> String {
+    let escape = |line: &str| {
+        let escaped = line
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\t', "\\t")
+            .replace('\r', "\\r")
+            .replace('\n', "\\n");
+        format!("\"{escaped}\"")
+    };
+
+    if text.contains('\n') {
+        let lines: Vec<String> = text.split_inclusive('\n').map(escape).collect();
+        format!("\"\"\n{}", lines.join("\n"))
+    } else {
+        escape(text)
+    }
+}
+
+/// A single entry in a PO file.
+#[derive(Debug, Default)]
+struct Entry {
+    context: Option<String>,
+    id: String,
+    text: String,
+    fuzzy: bool,
+}
+
+/// The field of a PO entry that the parser is currently reading.
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Context,
+    Id,
+    Text,
+}
+
+/// Split the PO file into its entries.
+fn parse_entries(text: &str) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut entry = Entry::default();
+    let mut field: Option<Field> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let line_number = index + 1;
+
+        if line.is_empty() {
+            continue;
+        }
+
+        // A comment or a new keyword after the translation starts the next entry.
+        if field == Some(Field::Text) && !line.starts_with('"') && !line.starts_with("msgstr") {
+            entries.push(mem::take(&mut entry));
+            field = None;
+        }
+
+        if let Some(comment) = line.strip_prefix('#') {
+            if let Some(flags) = comment.strip_prefix(',') {
+                entry.fuzzy |= flags.split(',').any(|flag| flag.trim() == "fuzzy");
+            }
+            continue;
+        }
+
+        let (value, target) = if line.starts_with('"') {
+            (line, field)
+        } else {
+            let (keyword, value) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| eyre!("Line {line_number}: missing value: {line}"))?;
+            let target = match keyword {
+                "msgctxt" => Field::Context,
+                "msgid" => Field::Id,
+                "msgstr" | "msgstr[0]" => Field::Text,
+                // Plural forms don't occur in the release notes.
+                "msgid_plural" => continue,
+                _ if keyword.starts_with("msgstr[") => continue,
+                _ => bail!("Line {line_number}: unknown keyword: {keyword}"),
+            };
+            (value.trim_start(), Some(target))
+        };
+
+        let value = unquote(value).wrap_err_with(|| format!("Line {line_number}"))?;
+
+        match target {
+            Some(Field::Context) => entry
+                .context
+                .get_or_insert_with(String::new)
+                .push_str(&value),
+            Some(Field::Id) => entry.id.push_str(&value),
+            Some(Field::Text) => entry.text.push_str(&value),
+            None => bail!("Line {line_number}: string outside of an entry: {line}"),
+        }
+        field = target;
+    }
+
+    if field.is_some() {
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/// Remove the quotes around a PO string and resolve its escape sequences.
+///
+/// PO strings use the C escape sequences. Octal and hexadecimal escapes
+/// stand for bytes, which together must form valid UTF-8.
+fn unquote(quoted: &str) -> Result<String> {
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .ok_or_else(|| eyre!("Invalid quoted string: {quoted}"))?;
+
+    let mut bytes: Vec<u8> = Vec::with_capacity(inner.len());
+    let mut chars = inner.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let unescaped = if c == '\\' {
+            match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('a') => '\x07',
+                Some('b') => '\x08',
+                Some('f') => '\x0C',
+                Some('v') => '\x0B',
+                Some(c @ ('"' | '\'' | '?' | '\\')) => c,
+                Some(first @ '0'..='7') => {
+                    // An octal escape has up to three digits.
+                    let mut value = first.to_digit(8).unwrap_or_default();
+                    for _ in 0..2 {
+                        match chars.peek().and_then(|c| c.to_digit(8)) {
+                            Some(digit) => {
+                                value = value * 8 + digit;
+                                chars.next();
+                            }
+                            None => break,
+                        }
+                    }
+                    bytes.push(escaped_byte(value, quoted)?);
+                    continue;
+                }
+                Some('x') => {
+                    let mut value = 0;
+                    let mut digits = 0;
+                    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(16)) {
+                        value = value * 16 + digit;
+                        digits += 1;
+                        chars.next();
+                    }
+                    if digits == 0 {
+                        bail!("Invalid escape sequence: \\x");
+                    }
+                    bytes.push(escaped_byte(value, quoted)?);
+                    continue;
+                }
+                other => bail!("Invalid escape sequence: \\{}", other.unwrap_or(' ')),
+            }
+        } else {
+            c
+        };
+        let mut buffer = [0; 4];
+        bytes.extend_from_slice(unescaped.encode_utf8(&mut buffer).as_bytes());
+    }
+
+    String::from_utf8(bytes).wrap_err_with(|| format!("Invalid UTF-8 in a quoted string: {quoted}"))
+}
+
+/// Check that the value of an octal or hexadecimal escape sequence fits in a byte.
+fn escaped_byte(value: u32, quoted: &str) -> Result<u8> {
+    u8::try_from(value).wrap_err_with(|| format!("Escape sequence out of range: {quoted}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ticket_abstraction::tests::ticket;
+
+    /// The PO template can be read back, and only translated,
+    /// up-to-date entries replace the English text.
+    #[test]
+    fn read_translations() {
+        let mut ticket = ticket("A-1");
+        ticket.doc_text = ".Fixed \"quotes\"\n\nThe tool no longer crashes.".to_string();
+
+        let messages = vec![
+            Message::section("bug-fixes", "title", "Bug fixes"),
+            Message::section("bug-fixes", "intro_abstract", "Fixed issues."),
+            Message::ticket(&ticket),
+        ];
+        let template = po_template(&messages);
+
+        // The untranslated template parses, and contains no translations.
+        let empty = Translations::parse(&template, "ja").expect("Failed to parse the template.");
+        assert!(empty.translations.is_empty());
+
+        // Translate the title and the doc text, mark the abstract as fuzzy.
+        let po = template
+            .replacen(
+                "msgid \"Bug fixes\"\nmsgstr \"\"",
+                "msgid \"Bug fixes\"\nmsgstr \"バグ修正\"",
+                1,
+            )
+            .replacen(
+                "#. The intro abstract of the bug-fixes section.",
+                "#, fuzzy",
+                1,
+            )
+            .replacen("msgid \"Fixed issues.\"\nmsgstr \"\"", "msgid \"Fixed issues.\"\nmsgstr \"修正\"", 1)
+            .replacen(
+                "\"The tool no longer crashes.\"\nmsgstr \"\"",
+                "\"The tool no longer crashes.\"\nmsgstr \"\"\n\".修正\\n\"\n\"\\n\"\n\"\\\"ツール\\\"\"",
+                1,
+            );
+        let translations = Translations::parse(&po, "ja").expect("Failed to parse translations.");
+
+        assert_eq!(
+            translations.get("section/bug-fixes/title", "Bug fixes"),
+            Some("バグ修正")
+        );
+        assert_eq!(
+            translations.get("section/bug-fixes/intro_abstract", "Fixed issues."),
+            None
+        );
+
+        let localized = localize_tickets(&[ticket.clone()], &translations);
+        assert_eq!(localized[0].doc_text, ".修正\n\n\"ツール\"");
+
+        // A changed English text falls back on English.
+        ticket.doc_text = "A different text.".to_string();
+        let localized = localize_tickets(&[ticket], &translations);
+        assert_eq!(localized[0].doc_text, "A different text.");
+    }
+
+    /// Texts with Windows line endings survive the export and the import,
+    /// and the parser accepts all C escape sequences.
+    #[test]
+    fn escape_sequences() {
+        let mut ticket = ticket("A-1");
+        ticket.doc_text = ".A title\r\n\r\nA \"quoted\"\tnote.\r\n".to_string();
+
+        let template = po_template(&[Message::ticket(&ticket)]);
+        assert!(!template.contains('\r'));
+        assert!(template.contains("\".A title\\r\\n\""));
+
+        // Use the English text as the translation.
+        let po = template.replacen(
+            "msgstr \"\"",
+            &format!("msgstr {}", quote(&ticket.doc_text)),
+            1,
+        );
+        let translations = Translations::parse(&po, "ja").expect("Failed to parse translations.");
+        let localized = localize_tickets(&[ticket.clone()], &translations);
+        assert_eq!(localized[0].doc_text, ticket.doc_text);
+
+        assert_eq!(
+            unquote(r#""\a\b\f\v\r\?\'\101\x42\0""#).expect("Failed to unquote."),
+            "\x07\x08\x0C\x0B\r?'AB\0"
+        );
+        // Octal escapes can encode the bytes of a multi-byte character.
+        assert_eq!(
+            unquote(r#""\343\201\202""#).expect("Failed to unquote."),
+            "あ"
+        );
+        assert!(unquote(r#""\q""#).is_err());
+        assert!(unquote(r#""\777""#).is_err());
+    }
+}